@@ -0,0 +1,159 @@
+//! Loads plugins with `dlopen` and exchanges trait castable objects with them.
+//!
+//! Only available on unix, since it relies on `dlopen`.
+#![cfg(unix)]
+#![feature(min_specialization, ptr_metadata)]
+#![allow(
+  unsafe_code,
+  reason = "Loading plugins and calling their functions requires unsafe code."
+)]
+
+extern crate alloc;
+
+use alloc::ffi::CString;
+use core::ffi::{CStr, c_char, c_int, c_void};
+use std::{
+  env::consts::{DLL_PREFIX, DLL_SUFFIX},
+  os::unix::ffi::OsStrExt,
+  path::{Path, PathBuf},
+  process::Command,
+};
+
+use trait_cast::{TraitcastableAny, make_trait_castable};
+use trait_cast_plugin_interface::{
+  CREATE_SYMBOL, Counter, CreateFn, Greeter, INTERFACE_ID, INTERFACE_ID_SYMBOL, InterfaceIdFn,
+  VISIT_SYMBOL, VisitFn,
+};
+
+const RTLD_NOW: c_int = 2;
+
+#[link(name = "dl")]
+unsafe extern "C" {
+  fn dlopen(filename: *const c_char, flag: c_int) -> *mut c_void;
+  fn dlsym(handle: *mut c_void, symbol: *const c_char) -> *mut c_void;
+  fn dlerror() -> *mut c_char;
+}
+
+/// Returns the message of the last `dl*` error.
+fn last_dl_error() -> String {
+  // SAFETY: `dlerror` has no preconditions.
+  let error = unsafe { dlerror() };
+  if error.is_null() {
+    "unknown error".to_string()
+  } else {
+    // SAFETY: `dlerror` returns either null or a valid C string.
+    unsafe { CStr::from_ptr(error) }
+      .to_string_lossy()
+      .into_owned()
+  }
+}
+
+/// A loaded plugin.
+///
+/// The library is never unloaded, because the objects created by it point to its vtables.
+#[derive(Debug, Clone, Copy)]
+pub struct Plugin {
+  create: CreateFn,
+  visit: VisitFn,
+}
+
+impl Plugin {
+  /// Loads the plugin at `path` and checks that it was compiled against the same interface.
+  ///
+  /// # Errors
+  /// In case the plugin can't be loaded, misses a symbol or was compiled against another interface.
+  ///
+  /// # Safety
+  /// The plugin must export the symbols of `trait_cast_plugin_interface` with their declared types
+  ///  and has to be compiled with the same compiler as the host.
+  pub unsafe fn load(path: &Path) -> Result<Self, String> {
+    let path = CString::new(path.as_os_str().as_bytes()).map_err(|err| err.to_string())?;
+    // SAFETY: `path` is a valid C string.
+    let handle = unsafe { dlopen(path.as_ptr(), RTLD_NOW) };
+    if handle.is_null() {
+      return Err(last_dl_error());
+    }
+    let symbol = |name: &CStr| {
+      // SAFETY: `handle` was returned by `dlopen` and `name` is a valid C string.
+      let symbol = unsafe { dlsym(handle, name.as_ptr()) };
+      if symbol.is_null() {
+        Err(last_dl_error())
+      } else {
+        Ok(symbol)
+      }
+    };
+
+    // SAFETY: The caller guarantees that the symbol has the declared type.
+    let interface_id =
+      unsafe { core::mem::transmute::<*mut c_void, InterfaceIdFn>(symbol(INTERFACE_ID_SYMBOL)?) };
+    // SAFETY: The function has no preconditions.
+    let plugin_interface_id = unsafe { interface_id() };
+    if plugin_interface_id != INTERFACE_ID.to_raw() {
+      return Err(format!(
+        "the plugin was compiled against the interface {plugin_interface_id:#018x}, but the host against {INTERFACE_ID}"
+      ));
+    }
+    Ok(Self {
+      // SAFETY: The caller guarantees that the symbol has the declared type.
+      create: unsafe { core::mem::transmute::<*mut c_void, CreateFn>(symbol(CREATE_SYMBOL)?) },
+      // SAFETY: The caller guarantees that the symbol has the declared type.
+      visit: unsafe { core::mem::transmute::<*mut c_void, VisitFn>(symbol(VISIT_SYMBOL)?) },
+    })
+  }
+
+  /// Creates an object of the plugin.
+  #[must_use]
+  pub fn create(&self) -> Box<dyn TraitcastableAny> {
+    (self.create)()
+  }
+
+  /// Lets the plugin use an object of the host and returns what it did with it.
+  pub fn visit(&self, object: &mut dyn TraitcastableAny) -> String {
+    (self.visit)(object)
+  }
+}
+
+/// An object of the host, that is passed to the plugin.
+#[make_trait_castable(#[stable] Greeter, #[stable] Counter)]
+#[derive(Debug, Default)]
+pub struct HostObject {
+  /// The current count.
+  pub count: u32,
+}
+
+impl Greeter for HostObject {
+  fn greet(&self) -> String {
+    "Hello from the host".to_string()
+  }
+}
+impl Counter for HostObject {
+  fn increment(&mut self) -> u32 {
+    self.count += 1;
+    self.count
+  }
+}
+
+/// Builds the example plugin with cargo and returns the path of the library.
+///
+/// Uses a separate target directory, because the one of the host may be locked by the running cargo.
+///
+/// # Errors
+/// In case cargo can't be run or fails to build the plugin.
+pub fn build_plugin() -> Result<PathBuf, String> {
+  let workspace = Path::new(env!("CARGO_MANIFEST_DIR")).join("../../..");
+  let target_dir = workspace.join("target/plugin-example");
+  let status = Command::new(env!("CARGO"))
+    .current_dir(&workspace)
+    .args(["build", "-p", "trait-cast-plugin-example", "--target-dir"])
+    .arg(&target_dir)
+    .status()
+    .map_err(|err| err.to_string())?;
+  if !status.success() {
+    return Err(format!("building the plugin failed with {status}"));
+  }
+  Ok(
+    target_dir
+      .join("debug")
+      .join(format!("{DLL_PREFIX}trait_cast_plugin_example{DLL_SUFFIX}")),
+  )
+}
@@ -0,0 +1,45 @@
+//! Loads the example plugin (or the plugin passed as the first argument) and casts objects both ways.
+#![feature(ptr_metadata)]
+#![allow(
+  unsafe_code,
+  reason = "Loading plugins and casting their objects by `StableId` requires unsafe code."
+)]
+
+#[cfg(unix)]
+use std::{env, path::PathBuf};
+
+#[cfg(unix)]
+use trait_cast_plugin_host::{HostObject, Plugin, build_plugin};
+#[cfg(unix)]
+use trait_cast_plugin_interface::{Counter, Greeter};
+
+/// # Errors
+/// In case the plugin can't be built or loaded.
+#[cfg(unix)]
+fn main() -> Result<(), String> {
+  let path = match env::args_os().nth(1) {
+    Some(path) => PathBuf::from(path),
+    None => build_plugin()?,
+  };
+  // SAFETY: The plugin is expected to be built from this workspace with the same compiler.
+  let plugin = unsafe { Plugin::load(&path) }?;
+
+  let mut plugin_object = plugin.create();
+  // SAFETY: `Plugin::load` checked that the plugin was compiled against the same interface.
+  let greeter = unsafe { plugin_object.downcast_ref_stable::<dyn Greeter>() }.unwrap();
+  println!("{}", greeter.greet());
+  // SAFETY: See above.
+  let counter = unsafe { plugin_object.downcast_mut_stable::<dyn Counter>() }.unwrap();
+  println!("The plugin object counted to {}", counter.increment());
+
+  let mut host_object = HostObject::default();
+  println!("The plugin {}", plugin.visit(&mut host_object));
+  println!("The host object counted to {}", host_object.count);
+  Ok(())
+}
+
+/// Plugins are loaded with `dlopen`, which is only available on unix.
+#[cfg(not(unix))]
+fn main() {
+  eprintln!("the plugin example is only supported on unix");
+}
@@ -0,0 +1,39 @@
+//! Builds the example plugin, loads it and casts objects both ways.
+#![allow(
+  unsafe_code,
+  reason = "Loading plugins and casting their objects by `StableId` requires unsafe code."
+)]
+
+use trait_cast_plugin_host::{HostObject, Plugin, build_plugin};
+use trait_cast_plugin_interface::{Counter, Greeter};
+
+#[test]
+fn test_plugin() {
+  let path = build_plugin().unwrap();
+  // SAFETY: The plugin was built from this workspace with the same compiler.
+  let plugin = unsafe { Plugin::load(&path) }.unwrap();
+
+  // Host casts plugin objects.
+  let mut plugin_object = plugin.create();
+  // SAFETY: `Plugin::load` checked that the plugin was compiled against the same interface.
+  let greeter = unsafe { plugin_object.downcast_ref_stable::<dyn Greeter>() }.unwrap();
+  assert_eq!(greeter.greet(), "Hello from the plugin");
+  // SAFETY: See above.
+  let counter = unsafe { plugin_object.downcast_mut_stable::<dyn Counter>() }.unwrap();
+  assert_eq!(counter.increment(), 1);
+  assert_eq!(counter.increment(), 2);
+
+  // Plugin casts host objects.
+  let mut host_object = HostObject::default();
+  assert_eq!(
+    plugin.visit(&mut host_object),
+    "greeted with \"Hello from the host\", counted to Some(1)"
+  );
+  assert_eq!(host_object.count, 1);
+
+  // Objects of the plugin can be passed back to it.
+  assert_eq!(
+    plugin.visit(plugin_object.as_mut()),
+    "greeted with \"Hello from the plugin\", counted to Some(3)"
+  );
+}
@@ -0,0 +1,14 @@
+//! Records the version of the compiler, which is part of `INTERFACE_ID`.
+use std::{env, process::Command};
+
+fn main() {
+  println!("cargo::rerun-if-env-changed=RUSTC");
+  let rustc = env::var_os("RUSTC").unwrap_or_else(|| "rustc".into());
+  let version = Command::new(rustc)
+    .arg("--version")
+    .output()
+    .ok()
+    .and_then(|output| String::from_utf8(output.stdout).ok())
+    .map_or_else(|| "unknown".to_owned(), |version| version.trim().to_owned());
+  println!("cargo::rustc-env=PLUGIN_EXAMPLE_RUSTC_VERSION={version}");
+}
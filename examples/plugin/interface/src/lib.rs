@@ -0,0 +1,50 @@
+//! The interface shared by the plugin host and its plugins.
+//!
+//! Both sides have to be compiled with the same compiler against the same version of this interface.
+//! This is checked by comparing their `INTERFACE_ID`s before any other function of the plugin is called.
+#![feature(ptr_metadata)]
+
+use core::ffi::CStr;
+
+use trait_cast::{StableId, StableTypeId, TraitcastableAny, traitcast};
+
+/// Implemented by objects of the host and of the plugins.
+#[traitcast(id = "trait_cast.plugin_example.Greeter.v1")]
+pub trait Greeter {
+  /// Returns a greeting.
+  fn greet(&self) -> String;
+}
+
+/// Implemented by objects of the host and of the plugins.
+#[traitcast(id = "trait_cast.plugin_example.Counter.v1")]
+pub trait Counter {
+  /// Increments the counter and returns the new count.
+  fn increment(&mut self) -> u32;
+}
+
+/// The id of this interface. Changes with the version of the interface, its traits, `trait-cast` and the compiler.
+pub const INTERFACE_ID: StableId = StableId::for_interface(
+  "trait_cast.plugin_example.v1",
+  env!("PLUGIN_EXAMPLE_RUSTC_VERSION"),
+  &[
+    <dyn Greeter as StableTypeId>::STABLE_ID,
+    <dyn Counter as StableTypeId>::STABLE_ID,
+  ],
+);
+
+/// Returns the raw `INTERFACE_ID` the plugin was compiled against.
+///
+/// Uses the C ABI, because it is called before the interface is known to match.
+pub type InterfaceIdFn = unsafe extern "C" fn() -> u64;
+/// The symbol of the `InterfaceIdFn` exported by plugins.
+pub const INTERFACE_ID_SYMBOL: &CStr = c"trait_cast_plugin_interface_id";
+
+/// Creates an object of the plugin.
+pub type CreateFn = fn() -> Box<dyn TraitcastableAny>;
+/// The symbol of the `CreateFn` exported by plugins.
+pub const CREATE_SYMBOL: &CStr = c"trait_cast_plugin_create";
+
+/// Lets the plugin use an object of the host and returns what it did with it.
+pub type VisitFn = fn(&mut dyn TraitcastableAny) -> String;
+/// The symbol of the `VisitFn` exported by plugins.
+pub const VISIT_SYMBOL: &CStr = c"trait_cast_plugin_visit";
@@ -0,0 +1,57 @@
+//! A plugin, that is loaded by `trait-cast-plugin-host` at runtime.
+#![feature(min_specialization, ptr_metadata)]
+#![allow(
+  unsafe_code,
+  reason = "Exporting symbols and casting objects of the host by their `StableId` requires unsafe code."
+)]
+
+use trait_cast::{TraitcastableAny, make_trait_castable};
+use trait_cast_plugin_interface::{Counter, Greeter, INTERFACE_ID};
+
+#[make_trait_castable(#[stable] Greeter, #[stable] Counter)]
+struct PluginObject {
+  count: u32,
+}
+
+impl Greeter for PluginObject {
+  fn greet(&self) -> String {
+    "Hello from the plugin".to_string()
+  }
+}
+impl Counter for PluginObject {
+  fn increment(&mut self) -> u32 {
+    self.count += 1;
+    self.count
+  }
+}
+
+/// See `trait_cast_plugin_interface::InterfaceIdFn`.
+#[unsafe(no_mangle)]
+pub const extern "C" fn trait_cast_plugin_interface_id() -> u64 {
+  INTERFACE_ID.to_raw()
+}
+
+/// See `trait_cast_plugin_interface::CreateFn`.
+#[unsafe(no_mangle)]
+#[expect(
+  clippy::no_mangle_with_rust_abi,
+  reason = "Only called after checking the interface id, the host is compiled with the same compiler."
+)]
+pub fn trait_cast_plugin_create() -> Box<dyn TraitcastableAny> {
+  Box::new(PluginObject { count: 0 })
+}
+
+/// See `trait_cast_plugin_interface::VisitFn`.
+#[unsafe(no_mangle)]
+#[expect(
+  clippy::no_mangle_with_rust_abi,
+  reason = "Only called after checking the interface id, the host is compiled with the same compiler."
+)]
+pub fn trait_cast_plugin_visit(object: &mut dyn TraitcastableAny) -> String {
+  // SAFETY: The host only calls this function after checking that we were compiled against the same interface.
+  let greeting = unsafe { object.downcast_ref_stable::<dyn Greeter>() }
+    .map_or_else(|| "nothing".to_string(), Greeter::greet);
+  // SAFETY: See above.
+  let count = unsafe { object.downcast_mut_stable::<dyn Counter>() }.map(Counter::increment);
+  format!("greeted with {greeting:?}, counted to {count:?}")
+}
@@ -0,0 +1,98 @@
+//! This example shows how to clone type erased traitcastable objects.
+#![cfg_attr(feature = "min_specialization", feature(min_specialization))]
+#![cfg_attr(feature = "downcast_unchecked", feature(downcast_unchecked))]
+#![feature(ptr_metadata)]
+
+use trait_cast::{
+  ClonableBox, TraitcastableAny, TraitcastableAnyInfra, TraitcastableCloneExt, make_trait_castable,
+};
+
+#[make_trait_castable(Dog, Clone)]
+#[derive(Clone)]
+struct HybridPet {
+  name: String,
+}
+
+impl Dog for HybridPet {
+  fn rename(&mut self, new_name: String) {
+    self.name = new_name;
+  }
+  fn bark(&self) {
+    println!("{}: Woof!", self.name);
+  }
+}
+
+#[make_trait_castable(Dog)]
+struct Stray;
+
+impl Dog for Stray {
+  fn rename(&mut self, _new_name: String) {}
+  fn bark(&self) {
+    println!("Stray: Woof!");
+  }
+}
+
+trait Dog {
+  fn rename(&mut self, new_name: String);
+  fn bark(&self);
+}
+
+mod sheep {
+  pub trait Clone {
+    fn clone_name(&self) -> String;
+  }
+}
+
+/// A bare `Clone` has to be the standard library trait, traits of your own with that name are listed by a path.
+#[make_trait_castable(sheep::Clone)]
+struct Dolly;
+
+impl sheep::Clone for Dolly {
+  fn clone_name(&self) -> String {
+    "Dolly II".to_string()
+  }
+}
+
+/// A type that can derive `Clone` because all its contents are clonable.
+#[derive(Clone)]
+struct Kennel {
+  pets: Vec<ClonableBox>,
+}
+
+#[cfg_attr(test, test)]
+fn main() {
+  let castable_pet: Box<dyn TraitcastableAny> = Box::new(HybridPet {
+    name: "Kokusnuss".to_string(),
+  });
+
+  let mut cloned_pet = castable_pet.try_clone_box().unwrap();
+  let as_dog: &mut dyn Dog = cloned_pet.downcast_mut().unwrap();
+  as_dog.rename("Rommel".to_string());
+
+  let as_dog: &dyn Dog = castable_pet.downcast_ref().unwrap();
+  as_dog.bark();
+  let as_dog: &dyn Dog = cloned_pet.downcast_ref().unwrap();
+  as_dog.bark();
+
+  // Types that didn't list `Clone` can't be cloned.
+  let stray: Box<dyn TraitcastableAny> = Box::new(Stray);
+  assert!(stray.try_clone_box().is_none());
+  assert!(ClonableBox::try_new(stray).is_err());
+  let dolly: Box<dyn TraitcastableAny> = Box::new(Dolly);
+  assert!(dolly.try_clone_box().is_none());
+  let as_sheep: &dyn sheep::Clone = dolly.downcast_ref().unwrap();
+  println!("Dolly was cloned into {}", as_sheep.clone_name());
+
+  let kennel = Kennel {
+    pets: vec![ClonableBox::try_new(castable_pet).unwrap()],
+  };
+  let mut kennel_copy = kennel.clone();
+  for pet in &mut kennel_copy.pets {
+    let as_dog: &mut dyn Dog = pet.downcast_mut().unwrap();
+    as_dog.rename("Kokusnuss II".to_string());
+  }
+  for pet in kennel.pets.iter().chain(&kennel_copy.pets) {
+    let as_dog: &dyn Dog = pet.downcast_ref().unwrap();
+    as_dog.bark();
+  }
+}
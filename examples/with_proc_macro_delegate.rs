@@ -0,0 +1,75 @@
+//! This example shows how to cast wrapper types to traits implemented by their fields.
+#![cfg_attr(feature = "min_specialization", feature(min_specialization))]
+#![cfg_attr(feature = "downcast_unchecked", feature(downcast_unchecked))]
+#![feature(ptr_metadata)]
+
+use trait_cast::{
+  TraitcastableAny, TraitcastableAnyInfra, TraitcastableAnyInfraExt, make_trait_castable,
+};
+
+trait Render {
+  fn render(&self) -> String;
+}
+trait Resize {
+  fn resize(&mut self, width: u32);
+}
+trait Log {
+  fn lines(&self) -> usize;
+}
+
+#[make_trait_castable(Resize)]
+struct Window {
+  width: u32,
+}
+impl Resize for Window {
+  fn resize(&mut self, width: u32) {
+    self.width = width;
+  }
+}
+
+struct Label(String);
+impl Render for Label {
+  fn render(&self) -> String {
+    self.0.clone()
+  }
+}
+
+/// Casts to `Render` return the label, all casts `Logged` has no target for are forwarded to the window.
+#[make_trait_castable(Log)]
+#[traitcast(delegate = window)]
+struct Logged {
+  log: Vec<String>,
+  #[traitcast(expose(Render))]
+  label: Label,
+  window: Window,
+}
+impl Log for Logged {
+  fn lines(&self) -> usize {
+    self.log.len()
+  }
+}
+
+#[cfg_attr(test, test)]
+fn main() {
+  let mut logged: Box<dyn TraitcastableAny> = Box::new(Logged {
+    log: vec!["created".to_string()],
+    label: Label("Hello".to_string()),
+    window: Window { width: 100 },
+  });
+
+  let log: &dyn Log = logged.downcast_ref().unwrap();
+  println!("{} lines", log.lines());
+  let render: &dyn Render = logged.downcast_ref().unwrap();
+  println!("{}", render.render());
+  let resize: &mut dyn Resize = logged.downcast_mut().unwrap();
+  resize.resize(200);
+  let logged_ref: &Logged = logged.downcast_ref().unwrap();
+  println!("width: {}", logged_ref.window.width);
+
+  // The box owns the `Logged` value and not its label, so it can't become a `Box<dyn Render>`.
+  let logged = TraitcastableAnyInfraExt::<dyn Render>::downcast(logged)
+    .err()
+    .unwrap();
+  let log: Box<dyn Log> = logged.downcast().ok().unwrap();
+  println!("{} lines", log.lines());
+}
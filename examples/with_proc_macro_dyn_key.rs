@@ -0,0 +1,38 @@
+//! This example shows how to use type erased traitcastable objects as `HashMap` keys.
+#![cfg_attr(feature = "min_specialization", feature(min_specialization))]
+#![cfg_attr(feature = "downcast_unchecked", feature(downcast_unchecked))]
+#![feature(ptr_metadata)]
+
+use core::hash::Hash;
+use std::collections::HashMap;
+
+use trait_cast::{DynKey, TraitcastableAny, make_trait_castable};
+
+#[make_trait_castable(PartialEq, Eq, Hash, PartialOrd)]
+#[derive(PartialEq, Eq, Hash, PartialOrd)]
+struct MeshPath(String);
+
+#[make_trait_castable(PartialEq, Eq, Hash, PartialOrd)]
+#[derive(PartialEq, Eq, Hash, PartialOrd)]
+struct TextureId(u32);
+
+#[cfg_attr(test, test)]
+fn main() {
+  let mut cache: HashMap<DynKey<Box<dyn TraitcastableAny>>, &str> = HashMap::new();
+  cache.insert(
+    DynKey(Box::new(MeshPath("dog.obj".to_string()))),
+    "dog mesh",
+  );
+  cache.insert(DynKey(Box::new(TextureId(7))), "dog texture");
+
+  let lookup: DynKey<Box<dyn TraitcastableAny>> = DynKey(Box::new(TextureId(7)));
+  assert_eq!(cache.get(&lookup), Some(&"dog texture"));
+
+  let lookup: DynKey<Box<dyn TraitcastableAny>> = DynKey(Box::new(MeshPath("cat.obj".to_string())));
+  assert_eq!(cache.get(&lookup), None);
+
+  let small = DynKey(Box::new(TextureId(1)) as Box<dyn TraitcastableAny>);
+  let large = DynKey(Box::new(TextureId(2)) as Box<dyn TraitcastableAny>);
+  assert!(small < large);
+  println!("{} cached entries", cache.len());
+}
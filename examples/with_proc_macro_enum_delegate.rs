@@ -0,0 +1,76 @@
+//! This example shows how to cast enums to traits implemented by the payload of their active variant.
+#![cfg_attr(feature = "min_specialization", feature(min_specialization))]
+#![cfg_attr(feature = "downcast_unchecked", feature(downcast_unchecked))]
+#![feature(ptr_metadata)]
+
+use trait_cast::{TraitcastableAny, TraitcastableAnyInfra, make_trait_castable};
+
+trait Area {
+  fn area(&self) -> f64;
+}
+trait Scale {
+  fn scale(&mut self, factor: f64);
+}
+
+#[make_trait_castable(Area, Scale)]
+struct Circle {
+  radius: f64,
+}
+impl Area for Circle {
+  fn area(&self) -> f64 {
+    core::f64::consts::PI * self.radius * self.radius
+  }
+}
+impl Scale for Circle {
+  fn scale(&mut self, factor: f64) {
+    self.radius *= factor;
+  }
+}
+
+#[make_trait_castable(Area)]
+struct Square {
+  side: f64,
+}
+impl Area for Square {
+  fn area(&self) -> f64 {
+    self.side * self.side
+  }
+}
+
+/// Casts are forwarded to the payload of the active variant, `Point` has none.
+#[make_trait_castable()]
+#[traitcast(delegate)]
+enum Shape {
+  Circle(Circle),
+  Square { square: Square },
+  Point,
+}
+
+#[cfg_attr(test, test)]
+fn main() {
+  let mut shapes: Vec<Box<dyn TraitcastableAny>> = vec![
+    Box::new(Shape::Circle(Circle { radius: 1.0 })),
+    Box::new(Shape::Square {
+      square: Square { side: 2.0 },
+    }),
+    Box::new(Shape::Point),
+  ];
+
+  for shape in &mut shapes {
+    let scale: Option<&mut dyn Scale> = shape.downcast_mut();
+    if let Some(scale) = scale {
+      scale.scale(2.0);
+    }
+    let area: Option<&dyn Area> = shape.downcast_ref();
+    match area {
+      Some(area) => println!("area: {:.2}", area.area()),
+      None => println!("no area"),
+    }
+  }
+
+  // Concrete types are not delegated, the boxes only contain `Shape`s.
+  let circle: Option<&Circle> = shapes[0].downcast_ref();
+  assert!(circle.is_none());
+  let point: Option<&Shape> = shapes[2].downcast_ref();
+  assert!(point.is_some());
+}
@@ -0,0 +1,58 @@
+//! This example shows how to make a cast target depend on the state of the object.
+#![cfg_attr(feature = "min_specialization", feature(min_specialization))]
+#![cfg_attr(feature = "downcast_unchecked", feature(downcast_unchecked))]
+#![feature(ptr_metadata)]
+
+use trait_cast::{
+  CastError, TraitcastableAny, TraitcastableAnyInfra, TraitcastableAnyInfraExt, make_trait_castable,
+};
+
+trait Print {
+  fn print(&self);
+}
+trait Editable {
+  fn append(&mut self, text: &str);
+}
+
+/// The document can only be edited while it is unlocked.
+#[make_trait_castable(Print, Editable if Self::is_unlocked)]
+struct Document {
+  locked: bool,
+  text: String,
+}
+impl Document {
+  const fn is_unlocked(&self) -> bool {
+    !self.locked
+  }
+}
+impl Print for Document {
+  fn print(&self) {
+    println!("{}", self.text);
+  }
+}
+impl Editable for Document {
+  fn append(&mut self, text: &str) {
+    self.text.push_str(text);
+  }
+}
+
+#[cfg_attr(test, test)]
+fn main() {
+  let mut document: Box<dyn TraitcastableAny> = Box::new(Document {
+    locked: true,
+    text: "Hello".to_string(),
+  });
+
+  match document.try_downcast_mut::<dyn Editable>() {
+    Err(error @ CastError::Rejected { .. }) => println!("{error}"),
+    _ => unreachable!("the document is locked"),
+  }
+
+  let concrete: &mut Document = document.downcast_mut().unwrap();
+  concrete.locked = false;
+  let editable: &mut dyn Editable = document.downcast_mut().unwrap();
+  editable.append(", world!");
+
+  let print: Box<dyn Print> = document.downcast().ok().unwrap();
+  print.print();
+}
@@ -0,0 +1,65 @@
+//! This example shows how to restrict casts to a target to the code owning a key type.
+#![cfg_attr(feature = "min_specialization", feature(min_specialization))]
+#![cfg_attr(feature = "downcast_unchecked", feature(downcast_unchecked))]
+#![feature(ptr_metadata)]
+
+use trait_cast::{TraitcastableAny, TraitcastableAnyInfra, make_trait_castable};
+
+mod auth {
+  use trait_cast::TraitcastableAny;
+
+  use crate::AdminControl;
+
+  /// Can only be constructed inside this module, because its field is private.
+  pub struct Key(());
+
+  /// Shuts the server down, if it supports it.
+  pub fn shutdown(server: &mut dyn TraitcastableAny) -> bool {
+    let Some(admin) = server.downcast_mut_scoped::<dyn AdminControl, _>(&Key(())) else {
+      return false;
+    };
+    admin.shutdown();
+    true
+  }
+}
+
+trait Status {
+  fn status(&self) -> String;
+}
+
+trait AdminControl {
+  fn shutdown(&mut self);
+}
+
+#[make_trait_castable(Status, AdminControl in auth::Key)]
+struct Server {
+  running: bool,
+}
+
+impl Status for Server {
+  fn status(&self) -> String {
+    format!("running: {}", self.running)
+  }
+}
+impl AdminControl for Server {
+  fn shutdown(&mut self) {
+    self.running = false;
+  }
+}
+
+#[cfg_attr(test, test)]
+fn main() {
+  let mut server: Box<dyn TraitcastableAny> = Box::new(Server { running: true });
+
+  // Everyone can cast to the regular targets, which are also the only ones listed by `Debug`.
+  let status: &dyn Status = server.downcast_ref().unwrap();
+  println!("{} {server:?}", status.status());
+
+  // But only the `auth` module can cast to `AdminControl`.
+  let admin: Option<&dyn AdminControl> = server.downcast_ref();
+  assert!(admin.is_none());
+  assert!(auth::shutdown(server.as_mut()));
+
+  let status: &dyn Status = server.downcast_ref().unwrap();
+  println!("{}", status.status());
+}
@@ -0,0 +1,75 @@
+//! This example shows how to identify cast targets and source types by ids that are stable across compilations.
+#![cfg_attr(feature = "min_specialization", feature(min_specialization))]
+#![cfg_attr(feature = "downcast_unchecked", feature(downcast_unchecked))]
+#![feature(ptr_metadata)]
+
+use trait_cast::{
+  StableId, StableIdRegistry, StableTypeId, TraitcastableAny, make_trait_castable, traitcast,
+};
+
+#[traitcast(id = "com.acme.Renderer.v1")]
+trait Renderer {
+  fn render(&self) -> String;
+}
+
+#[traitcast(id = "com.acme.AudioSink.v1")]
+trait AudioSink {
+  fn play(&self) -> String;
+}
+
+#[traitcast(id = 0x0001_0000)]
+#[make_trait_castable(Renderer, AudioSink)]
+struct Television;
+
+impl Renderer for Television {
+  fn render(&self) -> String {
+    "Television: rendering".to_string()
+  }
+}
+impl AudioSink for Television {
+  fn play(&self) -> String {
+    "Television: playing".to_string()
+  }
+}
+
+#[traitcast(id = "com.acme.Renderer.v2")]
+trait RendererV2 {}
+
+#[cfg_attr(test, test)]
+fn main() {
+  let mut registry = StableIdRegistry::new();
+  registry
+    .register_target::<dyn Renderer>()
+    .unwrap()
+    .register_target::<dyn AudioSink>()
+    .unwrap()
+    .register_source::<Television>()
+    .unwrap();
+
+  // The ids can be persisted, for example in a save file.
+  let castable: Box<dyn TraitcastableAny> = Box::new(Television);
+  let source_id = registry.source_id_of(castable.as_ref()).unwrap();
+  let target_ids: Vec<u64> = registry
+    .target_ids_of(castable.as_ref())
+    .map(StableId::to_raw)
+    .collect();
+  println!("source {source_id} implements {target_ids:x?}");
+
+  // And later be used to look up the cast target again.
+  let renderer_id = StableId::from_name("com.acme.Renderer.v1");
+  let erased = registry
+    .find_target(castable.as_ref(), renderer_id)
+    .unwrap();
+  println!("{}", erased.downcast::<dyn Renderer>().unwrap().render());
+  let audio_sink_id = StableId::from_name("com.acme.AudioSink.v1");
+  let erased = registry
+    .find_target(castable.as_ref(), audio_sink_id)
+    .unwrap();
+  println!("{}", erased.downcast::<dyn AudioSink>().unwrap().play());
+
+  // Reusing an id for another target is detected at registration.
+  let error = registry
+    .register_target_with_id::<dyn RendererV2>(<dyn Renderer as StableTypeId>::STABLE_ID)
+    .unwrap_err();
+  println!("{error}");
+}
@@ -0,0 +1,89 @@
+/* Generated by cbindgen from `src/ffi.rs`. Do not edit. */
+
+#ifndef TRAIT_CAST_H
+#define TRAIT_CAST_H
+
+#include <stdbool.h>
+#include <stdint.h>
+
+/**
+ * A reference counted handle to a trait castable object, which C code holds as an opaque pointer.
+ *
+ * The handle either refers to the object itself or to one of its interfaces.
+ * Every handle has its own reference count, but shares the object with the handles it was queried from.
+ */
+typedef struct TraitcastHandle TraitcastHandle;
+
+#ifdef __cplusplus
+extern "C" {
+#endif // __cplusplus
+
+/**
+ * Increments the reference count of the handle and returns it.
+ *
+ * Returns null if the handle is null.
+ *
+ * # Safety
+ * The handle must be null or a handle, that was not released yet.
+ */
+const struct TraitcastHandle *trait_cast_handle_retain(const struct TraitcastHandle *handle);
+
+/**
+ * Decrements the reference count of the handle and drops it once the count reaches zero.
+ *
+ * The object is dropped once all handles to it are dropped. Does nothing if the handle is null.
+ *
+ * # Safety
+ * The handle must be null or a handle, that was not released yet.
+ * It must not be used afterwards, unless it was retained before.
+ */
+void trait_cast_handle_release(const struct TraitcastHandle *handle);
+
+/**
+ * Returns whether the object of the handle supports the interface with the raw `StableId`.
+ *
+ * Returns false if the handle is null.
+ *
+ * # Safety
+ * The handle must be null or a handle, that was not released yet.
+ */
+bool trait_cast_handle_supports(const struct TraitcastHandle *handle, uint64_t interface_id);
+
+/**
+ * Returns a new handle for the interface with the raw `StableId` of the object of the handle.
+ *
+ * The new handle has a reference count of one and must be released separately.
+ * Returns null if the object doesn't support the interface or the handle is null.
+ *
+ * # Safety
+ * The handle must be null or a handle, that was not released yet.
+ */
+const struct TraitcastHandle *trait_cast_handle_query_interface(const struct TraitcastHandle *handle,
+                                                                uint64_t interface_id);
+
+/**
+ * Returns the raw `StableId` of the interface of the handle.
+ *
+ * Returns zero for handles to the object itself and null handles.
+ *
+ * # Safety
+ * The handle must be null or a handle, that was not released yet.
+ */
+uint64_t trait_cast_handle_interface_id(const struct TraitcastHandle *handle);
+
+/**
+ * Returns whether both handles refer to the same object.
+ *
+ * Returns false if any of the handles is null.
+ *
+ * # Safety
+ * The handles must be null or handles, that were not released yet.
+ */
+bool trait_cast_handle_same_object(const struct TraitcastHandle *a,
+                                   const struct TraitcastHandle *b);
+
+#ifdef __cplusplus
+}  // extern "C"
+#endif  // __cplusplus
+
+#endif  /* TRAIT_CAST_H */
@@ -6,41 +6,371 @@
 use cargo_manifest_proc_macros::CargoManifest;
 use proc_macro::TokenStream as TokenStream1;
 use proc_macro2::TokenStream as TokenStream2;
-use quote::quote;
+use quote::{ToTokens, quote, quote_spanned};
 use syn::{
-  Error, ItemEnum, ItemStruct, Token, TypePath,
+  Attribute, Error, Expr, ExprLit, Generics, Ident, Index, ItemEnum, ItemStruct, ItemTrait, Lit,
+  Member, Meta, MetaNameValue, Token, TypePath, parenthesized,
   parse::{self, Parse, ParseStream},
   parse_macro_input,
   punctuated::Punctuated,
+  spanned::Spanned,
 };
 use tracing_proc_macros_ink::proc_macro_logger_default_setup;
 
-/// Parses a list of `TypePath`s separated by commas.
+/// Parses a `TypePath` optionally prefixed with `#[stable]` and followed by `in <key>` or `if <guard>`.
+struct TraitCastTarget {
+  attrs: Vec<Attribute>,
+  path: TypePath,
+  key: Option<TypePath>,
+  guard: Option<Expr>,
+}
+
+impl Parse for TraitCastTarget {
+  fn parse(input: ParseStream<'_>) -> parse::Result<Self> {
+    let attrs = input.call(Attribute::parse_outer)?;
+    for attr in &attrs {
+      if !matches!(attr.meta, Meta::Path(ref path) if path.is_ident("stable")) {
+        return Err(Error::new(attr.span(), "Expected `#[stable]`"));
+      }
+    }
+    let path = input.parse()?;
+    let key = if input.peek(Token![in]) {
+      let in_token: Token![in] = input.parse()?;
+      if let Some(attr) = attrs.first() {
+        return Err(Error::new(
+          attr.span().join(in_token.span).unwrap_or(in_token.span),
+          "Scoped targets can't be `#[stable]`",
+        ));
+      }
+      Some(input.parse()?)
+    } else {
+      None
+    };
+    let guard = if input.peek(Token![if]) {
+      let if_token: Token![if] = input.parse()?;
+      if key.is_some() {
+        return Err(Error::new(if_token.span, "Scoped targets can't be guarded"));
+      }
+      Some(input.parse()?)
+    } else {
+      None
+    };
+    Ok(Self {
+      attrs,
+      path,
+      key,
+      guard,
+    })
+  }
+}
+
+impl ToTokens for TraitCastTarget {
+  fn to_tokens(&self, tokens: &mut TokenStream2) {
+    let attrs = &self.attrs;
+    let path = &self.path;
+    tokens.extend(quote!(#(#attrs)* #path));
+  }
+}
+
+/// Parses a list of `TraitCastTarget`s separated by commas.
 struct TraitCastTargets {
-  targets: Vec<TypePath>,
+  targets: Vec<TraitCastTarget>,
 }
 
 impl Parse for TraitCastTargets {
   fn parse(input: ParseStream<'_>) -> parse::Result<Self> {
-    let targets: Vec<TypePath> = Punctuated::<TypePath, Token![,]>::parse_terminated(input)?
-      .into_iter()
-      .collect();
+    let targets: Vec<TraitCastTarget> =
+      Punctuated::<TraitCastTarget, Token![,]>::parse_terminated(input)?
+        .into_iter()
+        .collect();
     Ok(Self { targets })
   }
 }
 
-impl quote::ToTokens for TraitCastTargets {
+impl ToTokens for TraitCastTargets {
+  /// Emits the targets in the syntax of `make_trait_castable_decl`:
+  ///  `(<targets>) in <key> => (<scoped targets>) ... if <guard> => (<guarded targets>) ...`.
   fn to_tokens(&self, tokens: &mut TokenStream2) {
-    let vars = &self.targets;
-    tokens.extend(quote!(#(#vars),*));
+    let vars = self
+      .targets
+      .iter()
+      .filter(|target| target.key.is_none() && target.guard.is_none());
+    tokens.extend(quote!((#(#vars),*)));
+
+    // The scoped targets are grouped by their key, in the order of the first appearance of the key.
+    let mut scoped: Vec<(String, &TypePath, Vec<&TypePath>)> = Vec::new();
+    for target in &self.targets {
+      let Some(key) = &target.key else {
+        continue;
+      };
+      let name = key.to_token_stream().to_string();
+      if let Some((_, _, paths)) = scoped.iter_mut().find(|(other, ..)| *other == name) {
+        paths.push(&target.path);
+      } else {
+        scoped.push((name, key, vec![&target.path]));
+      }
+    }
+    for (_, key, paths) in scoped {
+      tokens.extend(quote!(in #key => (#(#paths),*)));
+    }
+
+    // The guarded targets are grouped by their guard the same way.
+    let mut guarded: Vec<(String, &Expr, Vec<&TraitCastTarget>)> = Vec::new();
+    for target in &self.targets {
+      let Some(guard) = &target.guard else {
+        continue;
+      };
+      let name = guard.to_token_stream().to_string();
+      if let Some((_, _, targets)) = guarded.iter_mut().find(|(other, ..)| *other == name) {
+        targets.push(target);
+      } else {
+        guarded.push((name, guard, vec![target]));
+      }
+    }
+    for (_, guard, targets) in guarded {
+      tokens.extend(quote!(if #guard => (#(#targets),*)));
+    }
   }
 }
 
+/// The targets of the fields of a struct or of the payloads of an enum, taken from its `traitcast` attributes.
+#[derive(Default)]
+struct FieldTargets {
+  /// The fields marked with `#[traitcast(expose(...))]` and their targets.
+  exposed: Vec<(Member, Vec<TypePath>)>,
+  /// The delegate in the syntax of `make_trait_castable_decl`.
+  /// Either the field named by `#[traitcast(delegate = ...)]` on a struct
+  ///  or the patterns binding the payloads of the variants of an enum marked with `#[traitcast(delegate)]`.
+  delegate: Option<TokenStream2>,
+}
+
+impl FieldTargets {
+  /// Removes the `delegate` attribute of the struct and the `expose` attributes of its fields and collects their targets.
+  ///
+  /// # Errors
+  /// In case an attribute is malformed or names a field that doesn't exist.
+  fn take(item_struct: &mut ItemStruct) -> parse::Result<Self> {
+    let mut field_targets = Self::default();
+    let mut members = Vec::new();
+    for (index, field) in item_struct.fields.iter_mut().enumerate() {
+      let member = field
+        .ident
+        .clone()
+        .map_or_else(|| Member::Unnamed(Index::from(index)), Member::Named);
+      let mut result = Ok(());
+      field.attrs.retain(|attr| {
+        if !attr.path().is_ident("traitcast") {
+          return true;
+        }
+        let targets = attr.parse_args_with(|input: ParseStream<'_>| {
+          let ident: Ident = input.parse()?;
+          if ident != "expose" {
+            return Err(Error::new(ident.span(), "Expected `expose(...)`"));
+          }
+          let content;
+          parenthesized!(content in input);
+          Punctuated::<TypePath, Token![,]>::parse_terminated(&content)
+        });
+        match targets {
+          Ok(targets) => field_targets
+            .exposed
+            .push((member.clone(), targets.into_iter().collect())),
+          Err(err) => result = Err(err),
+        }
+        false
+      });
+      result?;
+      members.push(member);
+    }
+
+    let mut result = Ok(());
+    item_struct.attrs.retain(|attr| {
+      if !attr.path().is_ident("traitcast") {
+        return true;
+      }
+      // Other `traitcast` attributes (like `id = ...`) are left for the `traitcast` attribute macro.
+      let delegate = attr.parse_args_with(|input: ParseStream<'_>| {
+        let ident: Ident = input.fork().parse()?;
+        if ident != "delegate" {
+          input.parse::<TokenStream2>()?;
+          return Ok(None);
+        }
+        input.parse::<Ident>()?;
+        input.parse::<Token![=]>()?;
+        let member: Member = input.parse()?;
+        if !members.contains(&member) {
+          return Err(Error::new(member.span(), "Expected a field of the struct"));
+        }
+        Ok(Some(member))
+      });
+      match delegate {
+        Ok(None) => true,
+        Ok(Some(_)) if field_targets.delegate.is_some() => {
+          result = Err(Error::new(attr.span(), "Duplicate `delegate`"));
+          false
+        },
+        Ok(Some(member)) => {
+          field_targets.delegate = Some(member.into_token_stream());
+          false
+        },
+        Err(err) => {
+          result = Err(err);
+          false
+        },
+      }
+    });
+    result?;
+    Ok(field_targets)
+  }
+
+  /// Removes the `delegate` attribute of the enum and delegates to the payloads of its variants.
+  ///
+  /// Only variants with exactly one field are delegated to.
+  ///
+  /// # Errors
+  /// In case the attribute is malformed or duplicated.
+  fn take_enum(item_enum: &mut ItemEnum) -> parse::Result<Self> {
+    let mut delegate = false;
+    let mut result = Ok(());
+    item_enum.attrs.retain(|attr| {
+      if !attr.path().is_ident("traitcast") {
+        return true;
+      }
+      // Other `traitcast` attributes (like `id = ...`) are left for the `traitcast` attribute macro.
+      let is_delegate = attr.parse_args_with(|input: ParseStream<'_>| {
+        let ident: Ident = input.fork().parse()?;
+        if ident != "delegate" {
+          input.parse::<TokenStream2>()?;
+          return Ok(false);
+        }
+        input.parse::<Ident>()?;
+        if !input.is_empty() {
+          return Err(Error::new(
+            input.span(),
+            "Enums delegate to the payloads of their variants, expected `#[traitcast(delegate)]`",
+          ));
+        }
+        Ok(true)
+      });
+      match is_delegate {
+        Ok(false) => true,
+        Ok(true) if delegate => {
+          result = Err(Error::new(attr.span(), "Duplicate `delegate`"));
+          false
+        },
+        Ok(true) => {
+          delegate = true;
+          false
+        },
+        Err(err) => {
+          result = Err(err);
+          false
+        },
+      }
+    });
+    result?;
+
+    if !delegate {
+      return Ok(Self::default());
+    }
+    let arms = item_enum.variants.iter().filter_map(|variant| {
+      let mut fields = variant.fields.iter();
+      let (Some(field), None) = (fields.next(), fields.next()) else {
+        return None;
+      };
+      let ident = &variant.ident;
+      Some(field.ident.as_ref().map_or_else(
+        || quote!(Self::#ident(payload) => payload),
+        |field_ident| quote!(Self::#ident { #field_ident: payload } => payload),
+      ))
+    });
+    Ok(Self {
+      exposed: Vec::new(),
+      delegate: Some(quote!({ #(#arms),* })),
+    })
+  }
+}
+
+impl ToTokens for FieldTargets {
+  /// Emits the targets in the syntax of `make_trait_castable_decl`: `expose <field> => (<targets>) ... delegate <field>`.
+  fn to_tokens(&self, tokens: &mut TokenStream2) {
+    for (member, targets) in &self.exposed {
+      tokens.extend(quote!(expose #member => (#(#targets),*)));
+    }
+    if let Some(delegate) = &self.delegate {
+      tokens.extend(quote!(delegate #delegate));
+    }
+  }
+}
+
+/// The standard library traits, that are replaced by cast targets: `(module in core, trait, cast target)`.
+const STD_CAPABILITIES: [(&str, &str, &str); 5] = [
+  ("clone", "Clone", "TraitcastableClone"),
+  ("cmp", "PartialEq", "TraitcastablePartialEq"),
+  ("cmp", "Eq", "TraitcastableEq"),
+  ("hash", "Hash", "TraitcastableHash"),
+  ("cmp", "PartialOrd", "TraitcastablePartialOrd"),
+];
+
+/// Returns the cast target replacing the standard library trait at `path`, like `Clone` or `core::clone::Clone`.
+///
+/// Bare names additionally return a check, which fails to compile if the name doesn't resolve to the standard library trait.
+fn std_capability(path: &syn::Path) -> Option<(Ident, Option<TokenStream2>)> {
+  let segments = path
+    .segments
+    .iter()
+    .map(|segment| segment.arguments.is_none().then_some(&segment.ident))
+    .collect::<Option<Vec<_>>>()?;
+  let (module, name) = match segments.as_slice() {
+    [name] if path.leading_colon.is_none() => (None, *name),
+    [krate, module, name] if *krate == "core" || *krate == "std" => (Some(*module), *name),
+    _ => return None,
+  };
+  let &(std_module, _, capability) =
+    STD_CAPABILITIES.iter().find(|(std_module, std_name, _)| {
+      name == std_name && module.is_none_or(|module| module == std_module)
+    })?;
+  let check = module.is_none().then(|| {
+    let std_module = Ident::new(std_module, name.span());
+    quote_spanned! {name.span()=>
+      const _: () = {
+        #[allow(dead_code)]
+        fn check<T: #name>() {
+          fn listed_trait_must_be_from_std<U: ::core::#std_module::#name>() {}
+          listed_trait_must_be_from_std::<T>();
+        }
+      };
+    }
+  });
+  Some((Ident::new(capability, name.span()), check))
+}
+
 /// Attribute macro implementing `TraitcastableAny` for a struct, enum or union.
 ///
 /// Use the arguments to specify all possible target Traits for witch trait objects are
 /// supposed to be downcastable from a dyn `TraitcastableAny`.
 ///
+/// Targets prefixed with `#[stable]` can also be found by their `StableId` (see `TraitcastTarget::from_stable`).
+/// Targets followed by `in <key>`, like `AdminControl in crate::auth::Key`, can only be cast to with a reference to the key
+///  (see `downcast_ref_scoped`). They are left out of the `Debug` output.
+///
+/// Targets followed by `if <guard>`, like `Editable if Self::is_unlocked`, can only be cast to while the guard returns true.
+///  The guard is called with a reference to the value on every cast, owned casts (like `Box::downcast`) honor it too.
+///  `try_downcast_ref` reports casts rejected by a guard as `CastError::Rejected`.
+///
+/// Fields of structs marked with `#[traitcast(expose(Render, ...))]` provide the listed targets,
+///  casts to them return a reference to the field. Owned casts (like `Box::downcast`) to field targets fail.
+/// `#[traitcast(delegate = field)]` placed below this attribute forwards casts to targets the struct doesn't list
+///  to the field, whose type has to implement `TraitcastableAny`.
+/// On enums `#[traitcast(delegate)]` forwards them to the payload of the active variant instead.
+/// Only variants with exactly one field are delegated to, the types of their fields have to implement `TraitcastableAny`.
+///
+/// Listing `Clone` makes `TraitcastableCloneExt::try_clone_box` work for the type.
+/// Listing `PartialEq` and `Eq` makes `DynKey` compare the concrete values, `Hash` and `PartialOrd` make it hash and order them.
+/// Bare names have to resolve to the standard library traits (`Hash` isn't part of the prelude), otherwise compilation fails.
+/// List traits of your own with the same names by a path, like `my_module::Clone`.
+/// Fully qualified paths like `core::clone::Clone` work too.
+///
 /// Example:
 /// ```no_build
 ///   extern crate trait_cast_rs;
@@ -77,16 +407,42 @@ pub fn make_trait_castable(args: TokenStream1, input: TokenStream1) -> TokenStre
   // Convert the input to a TokenStream2
   let input = TokenStream2::from(input);
 
-  let trait_cast_targets = parse_macro_input!(args as TraitCastTargets);
+  let mut trait_cast_targets = parse_macro_input!(args as TraitCastTargets);
+
+  // The standard library traits are not object safe,
+  //  so they are replaced with the cast targets recording how to use them on the concrete type.
+  // Bare names are checked to resolve to the standard library traits, so user defined traits aren't replaced silently.
+  let mut std_checks = TokenStream2::new();
+  for target in &mut trait_cast_targets.targets {
+    let target = &mut target.path;
+    if target.qself.is_some() {
+      continue;
+    }
+    let Some((capability, check)) = std_capability(&target.path) else {
+      continue;
+    };
+    std_checks.extend(check);
+    *target = syn::parse_quote!(#crate_path::#capability);
+  }
 
   // First, try to parse the input as a struct
   let input_struct = syn::parse2::<ItemStruct>(input.clone());
-  let mut source_ident = input_struct.map(|item_struct| item_struct.ident);
+  let mut field_targets = FieldTargets::default();
+  let mut input = input;
+  let mut source_ident = input_struct.and_then(|mut item_struct| {
+    field_targets = FieldTargets::take(&mut item_struct)?;
+    input = item_struct.to_token_stream();
+    Ok(item_struct.ident)
+  });
 
   // Maybe it's an enum
   if source_ident.is_err() {
     let input_enum = syn::parse2::<ItemEnum>(input.clone());
-    source_ident = input_enum.map(|item_enum| item_enum.ident);
+    source_ident = input_enum.and_then(|mut item_enum| {
+      field_targets = FieldTargets::take_enum(&mut item_enum)?;
+      input = item_enum.to_token_stream();
+      Ok(item_enum.ident)
+    });
   }
 
   if let Err(err) = source_ident {
@@ -99,7 +455,120 @@ pub fn make_trait_castable(args: TokenStream1, input: TokenStream1) -> TokenStre
 
   TokenStream1::from(quote!(
     #input
+    #std_checks
     #crate_path::make_trait_castable_decl! {
-    #source_ident => (#trait_cast_targets)
+    #source_ident => #trait_cast_targets #field_targets
   }))
 }
+
+/// Parses the `key = value` arguments of the `traitcast` attribute.
+struct TraitcastArgs {
+  id: Option<Expr>,
+}
+
+impl Parse for TraitcastArgs {
+  fn parse(input: ParseStream<'_>) -> parse::Result<Self> {
+    let mut id = None;
+    for arg in Punctuated::<Meta, Token![,]>::parse_terminated(input)? {
+      if arg.path().is_ident("delegate") {
+        return Err(Error::new(
+          arg.path().span(),
+          "`#[traitcast(delegate)]` has to be placed below `#[make_trait_castable(...)]`",
+        ));
+      }
+      let arg: MetaNameValue = match arg {
+        Meta::NameValue(arg) => arg,
+        _ => return Err(Error::new(arg.span(), "Expected `id = ...`")),
+      };
+      if !arg.path.is_ident("id") {
+        return Err(Error::new(arg.path.span(), "Expected `id`"));
+      }
+      if id.is_some() {
+        return Err(Error::new(arg.path.span(), "Duplicate `id`"));
+      }
+      id = Some(arg.value);
+    }
+    Ok(Self { id })
+  }
+}
+
+/// Attribute macro assigning a `StableId` to a trait, struct or enum by implementing `StableTypeId`.
+///
+/// For traits `StableTypeId` is implemented on the trait object type `dyn Trait`.
+///
+/// The id is either the hash of a declared name (`id = "com.acme.Renderer.v1"`)
+/// or an explicitly chosen number (`id = 0x1234`).
+///
+/// Example:
+/// ```no_build
+///   #[traitcast(id = "com.acme.Renderer.v1")]
+///   trait Renderer {
+///     fn render(&self);
+///   }
+///
+///   #[traitcast(id = 42)]
+///   #[make_trait_castable(Renderer)]
+///   struct Canvas;
+/// ```
+#[proc_macro_attribute]
+pub fn traitcast(args: TokenStream1, input: TokenStream1) -> TokenStream1 {
+  proc_macro_logger_default_setup();
+
+  let cargo_manifest = CargoManifest::shared();
+  let crate_path = cargo_manifest.resolve_crate_path("trait-cast", &[]);
+
+  let input = TokenStream2::from(input);
+  let args = parse_macro_input!(args as TraitcastArgs);
+
+  let Some(id) = args.id else {
+    return Error::new(input.span(), "Expected `id = ...`")
+      .to_compile_error()
+      .into();
+  };
+  let stable_id = match &id {
+    Expr::Lit(ExprLit {
+      lit: Lit::Str(name),
+      ..
+    }) => quote!(#crate_path::StableId::from_name(#name)),
+    Expr::Lit(ExprLit {
+      lit: Lit::Int(raw), ..
+    }) => quote!(#crate_path::StableId::from_raw(#raw)),
+    _ => {
+      return Error::new(id.span(), "Expected a string or integer literal")
+        .to_compile_error()
+        .into();
+    },
+  };
+
+  let (self_ty, generics): (TokenStream2, Generics) =
+    if let Ok(item_trait) = syn::parse2::<ItemTrait>(input.clone()) {
+      let ident = item_trait.ident;
+      (quote!(dyn #ident), item_trait.generics)
+    } else if let Ok(item_struct) = syn::parse2::<ItemStruct>(input.clone()) {
+      let ident: Ident = item_struct.ident;
+      (quote!(#ident), item_struct.generics)
+    } else if let Ok(item_enum) = syn::parse2::<ItemEnum>(input.clone()) {
+      let ident = item_enum.ident;
+      (quote!(#ident), item_enum.generics)
+    } else {
+      return Error::new(input.span(), "Expected a trait, struct or enum")
+        .to_compile_error()
+        .into();
+    };
+
+  if !generics.params.is_empty() {
+    return Error::new(
+      generics.span(),
+      "Generic items can't have a stable id, implement `StableTypeId` for the concrete types manually",
+    )
+    .to_compile_error()
+    .into();
+  }
+
+  TokenStream1::from(quote!(
+    #input
+    impl #crate_path::StableTypeId for #self_ty {
+      const STABLE_ID: #crate_path::StableId = #stable_id;
+    }
+  ))
+}
@@ -0,0 +1,203 @@
+use core::{
+  any::{Any, TypeId},
+  fmt::{self, Debug, Formatter},
+  ops::Deref,
+};
+
+use alloc::{boxed::Box, collections::BTreeMap, sync::Arc, vec::Vec};
+
+use crate::{TraitcastableAny, TraitcastableAnyInfra, TraitcastableAnyInfraExt};
+
+type RefAdapter<Target> = Box<dyn Fn(&dyn TraitcastableAny) -> Option<Box<Target>>>;
+type BoxAdapter<Target> =
+  Box<dyn Fn(Box<dyn TraitcastableAny>) -> Result<Box<Target>, Box<dyn TraitcastableAny>>>;
+type ArcAdapter<Target> =
+  Box<dyn Fn(Arc<dyn TraitcastableAny>) -> Result<Arc<Target>, Arc<dyn TraitcastableAny>>>;
+
+/// The adapters to one target, in the order of their registration.
+struct Adapters<Target: ?Sized + 'static> {
+  refs: Vec<RefAdapter<Target>>,
+  boxes: Vec<BoxAdapter<Target>>,
+  arcs: Vec<ArcAdapter<Target>>,
+}
+
+impl<Target: ?Sized + 'static> Default for Adapters<Target> {
+  fn default() -> Self {
+    Self {
+      refs: Vec::new(),
+      boxes: Vec::new(),
+      arcs: Vec::new(),
+    }
+  }
+}
+
+/// The result of `AdapterRegistry::cast_or_adapt`. Either borrows the object or owns the adapter created for it.
+#[derive(Debug)]
+pub enum Adapted<'a, Target: ?Sized> {
+  /// The object was castable to `Target` itself.
+  Borrowed(&'a Target),
+  /// The object was adapted to `Target`.
+  Owned(Box<Target>),
+}
+
+impl<Target: ?Sized> Adapted<'_, Target> {
+  /// Returns true if the object was adapted.
+  #[must_use]
+  pub const fn is_adapted(&self) -> bool {
+    matches!(self, Self::Owned(_))
+  }
+}
+
+impl<Target: ?Sized> Deref for Adapted<'_, Target> {
+  type Target = Target;
+
+  fn deref(&self) -> &Target {
+    match self {
+      Self::Borrowed(target) => target,
+      Self::Owned(target) => target,
+    }
+  }
+}
+
+/// A registry of conversion functions, which adapt objects to traits they don't implement.
+///
+/// Useful for migrations, where old objects implement `dyn RendererV1` but callers ask for `dyn RendererV2`.
+/// The casts first try the cast targets of the object and only then the adapters in the order of their registration.
+#[derive(Default)]
+pub struct AdapterRegistry {
+  /// Maps the `TypeId` of the target to its `Adapters<Target>`.
+  adapters: BTreeMap<TypeId, Box<dyn Any>>,
+}
+
+impl Debug for AdapterRegistry {
+  fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+    f.debug_struct("AdapterRegistry")
+      .field("targets", &self.adapters.len())
+      .finish_non_exhaustive()
+  }
+}
+
+impl AdapterRegistry {
+  /// Creates a registry without adapters.
+  #[must_use]
+  pub const fn new() -> Self {
+    Self {
+      adapters: BTreeMap::new(),
+    }
+  }
+
+  fn adapters<Target: ?Sized + 'static>(&self) -> Option<&Adapters<Target>> {
+    self
+      .adapters
+      .get(&TypeId::of::<Target>())
+      .and_then(|adapters| adapters.downcast_ref())
+  }
+
+  fn adapters_mut<Target: ?Sized + 'static>(&mut self) -> &mut Adapters<Target> {
+    self
+      .adapters
+      .entry(TypeId::of::<Target>())
+      .or_insert_with(|| Box::new(Adapters::<Target>::default()))
+      .downcast_mut()
+      .expect("the adapters are keyed by the `TypeId` of their target")
+  }
+
+  /// Registers an adapter from references to `From` to `To`, which is used by `cast_or_adapt`.
+  pub fn register<From: ?Sized + 'static, To: ?Sized + 'static>(
+    &mut self,
+    adapter: impl Fn(&From) -> Box<To> + 'static,
+  ) -> &mut Self {
+    self.adapters_mut::<To>().refs.push(Box::new(move |object| {
+      Some(adapter(object.downcast_ref()?))
+    }));
+    self
+  }
+
+  /// Registers an adapter from `Box<From>` to `Box<To>`, which is used by `cast_or_adapt_box`.
+  pub fn register_box<From: ?Sized + 'static, To: ?Sized + 'static>(
+    &mut self,
+    adapter: impl Fn(Box<From>) -> Box<To> + 'static,
+  ) -> &mut Self {
+    self
+      .adapters_mut::<To>()
+      .boxes
+      .push(Box::new(move |object| object.downcast().map(&adapter)));
+    self
+  }
+
+  /// Registers an adapter from `Arc<From>` to `Arc<To>`, which is used by `cast_or_adapt_arc`.
+  pub fn register_arc<From: ?Sized + 'static, To: ?Sized + 'static>(
+    &mut self,
+    adapter: impl Fn(Arc<From>) -> Arc<To> + 'static,
+  ) -> &mut Self {
+    self
+      .adapters_mut::<To>()
+      .arcs
+      .push(Box::new(move |object| object.downcast().map(&adapter)));
+    self
+  }
+
+  /// Casts the object to `Target`, or adapts it with the first matching adapter registered with `register`.
+  ///
+  /// Returns `None` if the object can neither be cast nor adapted to `Target`.
+  #[must_use]
+  pub fn cast_or_adapt<'a, Target: ?Sized + 'static>(
+    &self,
+    object: &'a dyn TraitcastableAny,
+  ) -> Option<Adapted<'a, Target>> {
+    if let Some(target) = object.downcast_ref() {
+      return Some(Adapted::Borrowed(target));
+    }
+    self
+      .adapters::<Target>()?
+      .refs
+      .iter()
+      .find_map(|adapter| adapter(object))
+      .map(Adapted::Owned)
+  }
+
+  /// Casts the box to `Target`, or adapts it with the first matching adapter registered with `register_box`.
+  ///
+  /// # Errors
+  /// In case the object can neither be cast nor adapted to `Target` the original box is returned as the error type.
+  pub fn cast_or_adapt_box<Target: ?Sized + 'static>(
+    &self,
+    object: Box<dyn TraitcastableAny>,
+  ) -> Result<Box<Target>, Box<dyn TraitcastableAny>> {
+    let adapters = self.adapters::<Target>();
+    adapt_owned(object, adapters.map_or(&[][..], |adapters| &adapters.boxes))
+  }
+
+  /// Casts the `Arc` to `Target`, or adapts it with the first matching adapter registered with `register_arc`.
+  ///
+  /// # Errors
+  /// In case the object can neither be cast nor adapted to `Target` the original `Arc` is returned as the error type.
+  pub fn cast_or_adapt_arc<Target: ?Sized + 'static>(
+    &self,
+    object: Arc<dyn TraitcastableAny>,
+  ) -> Result<Arc<Target>, Arc<dyn TraitcastableAny>> {
+    let adapters = self.adapters::<Target>();
+    adapt_owned(object, adapters.map_or(&[][..], |adapters| &adapters.arcs))
+  }
+}
+
+/// Casts the pointer to `Target`, or adapts it with the first matching adapter.
+///
+/// # Errors
+/// In case the object can neither be cast nor adapted to `Target` the original pointer is returned as the error type.
+fn adapt_owned<P: TraitcastableAnyInfraExt<Target, Output = O>, Target: ?Sized + 'static, O>(
+  object: P,
+  adapters: &[Box<dyn Fn(P) -> Result<O, P>>],
+) -> Result<O, P> {
+  let mut object = match object.downcast() {
+    Ok(target) => return Ok(target),
+    Err(object) => object,
+  };
+  for adapter in adapters {
+    object = match adapter(object) {
+      Ok(target) => return Ok(target),
+      Err(object) => object,
+    };
+  }
+  Err(object)
+}
@@ -0,0 +1,85 @@
+use core::{
+  any::{TypeId, type_name},
+  error::Error,
+  fmt::{self, Display, Formatter},
+};
+
+use crate::{TraitcastableAny, TraitcastableAnyInfra};
+
+/// Error returned by `try_downcast_ref` and `try_downcast_mut` explaining why a cast failed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CastError {
+  /// The object has no target for the type and doesn't provide a value of it.
+  NotCastable {
+    /// The `type_name` of the requested type.
+    type_name: &'static str,
+  },
+  /// The object has a target for the type, but its guard rejects casts to it in the current state of the object.
+  Rejected {
+    /// The `type_name` of the requested type.
+    type_name: &'static str,
+  },
+}
+
+impl CastError {
+  /// Returns the `type_name` of the type, that was requested.
+  #[must_use]
+  pub const fn type_name(&self) -> &'static str {
+    match self {
+      Self::NotCastable { type_name } | Self::Rejected { type_name } => type_name,
+    }
+  }
+
+  /// Returns the reason why a cast of the object to `Target` fails.
+  fn of<Target: ?Sized + 'static>(object: &dyn TraitcastableAny) -> Self {
+    let target = TypeId::of::<Target>();
+    let type_name = type_name::<Target>();
+    if object.find_traitcast_target(target).is_some() && !object.traitcast_allowed(target) {
+      Self::Rejected { type_name }
+    } else {
+      Self::NotCastable { type_name }
+    }
+  }
+}
+
+impl Display for CastError {
+  fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+    match self {
+      Self::NotCastable { type_name } => write!(f, "the object can't be cast to `{type_name}`"),
+      Self::Rejected { type_name } => write!(
+        f,
+        "the guard of `{type_name}` rejects the cast in the current state of the object"
+      ),
+    }
+  }
+}
+
+impl Error for CastError {}
+
+macro_rules! implement_try_downcast_with_markers {
+  ($($(+)? $traits:ident)*) => {
+    impl dyn TraitcastableAny $(+ $traits)* {
+      /// Same as `downcast_ref`, except that it explains why the cast failed.
+      ///
+      /// # Errors
+      /// `CastError::Rejected` if the guard of the target rejects the cast, otherwise `CastError::NotCastable`.
+      pub fn try_downcast_ref<Target: ?Sized + 'static $(+ $traits)*>(&self) -> Result<&Target, CastError> {
+        TraitcastableAnyInfra::<Target>::downcast_ref(self).ok_or_else(|| CastError::of::<Target>(self))
+      }
+
+      /// Same as `downcast_mut`, except that it explains why the cast failed.
+      ///
+      /// # Errors
+      /// `CastError::Rejected` if the guard of the target rejects the cast, otherwise `CastError::NotCastable`.
+      pub fn try_downcast_mut<Target: ?Sized + 'static $(+ $traits)*>(&mut self) -> Result<&mut Target, CastError> {
+        // The reason is determined up front, since the borrow checker keeps `self` borrowed after a failed `downcast_mut`.
+        let error = CastError::of::<Target>(self);
+        TraitcastableAnyInfra::<Target>::downcast_mut(self).ok_or(error)
+      }
+    }
+  };
+}
+
+implement_try_downcast_with_markers!();
+implement_try_downcast_with_markers!(Send);
+implement_try_downcast_with_markers!(Send + Sync);
@@ -0,0 +1,146 @@
+use core::{
+  fmt::{self, Debug, Formatter},
+  ops::{Deref, DerefMut},
+  ptr,
+};
+
+use alloc::boxed::Box;
+
+use crate::{TraitcastableAny, TraitcastableAnyInfra};
+
+mod sealed {
+  pub trait Sealed {}
+  impl<T: Clone> Sealed for T {}
+}
+
+/// Cast target recording how to clone the concrete type.
+///
+/// List it as a target (or `Clone` when using the `make_trait_castable` attribute macro)
+///  to make `try_clone_box` work for a type.
+///
+/// This trait is sealed and implemented for all `Clone` types.
+pub trait TraitcastableClone: sealed::Sealed {
+  /// Clones the value into a new `Box` and returns its data pointer.
+  #[doc(hidden)]
+  fn clone_to_raw(&self) -> *mut ();
+}
+
+impl<T: Clone> TraitcastableClone for T {
+  fn clone_to_raw(&self) -> *mut () {
+    Box::into_raw(Box::new(self.clone())).cast::<()>()
+  }
+}
+
+/// Extension Trait to clone a `Box<dyn TraitcastableAny>` (and its `Send`/`Sync` variants).
+pub trait TraitcastableCloneExt: Sized {
+  /// Returns true if the concrete type can be cast to `TraitcastableClone`.
+  fn is_clonable(&self) -> bool;
+
+  /// Clones the concrete value into a new `Box`.
+  ///
+  /// Returns `None` if the concrete type can't be cast to `TraitcastableClone`.
+  fn try_clone_box(&self) -> Option<Self>;
+}
+
+/// Casts the object to `TraitcastableClone`, if the target clones the object itself and not a value owned by it.
+fn cloner(castable: &dyn TraitcastableAny) -> Option<&dyn TraitcastableClone> {
+  castable.downcast_ref_in_place()
+}
+
+macro_rules! implement_clone_with_markers {
+  ($($(+)? $traits:ident)*) => {
+    impl TraitcastableCloneExt for Box<dyn TraitcastableAny $(+ $traits)*> {
+      fn is_clonable(&self) -> bool {
+        let castable: &dyn TraitcastableAny = self.as_ref();
+        cloner(castable).is_some()
+      }
+
+      fn try_clone_box(&self) -> Option<Self> {
+        let castable: &dyn TraitcastableAny = self.as_ref();
+        let cloner = cloner(castable)?;
+        let metadata = ptr::metadata(self.as_ref());
+        // SAFETY:
+        // `clone_to_raw` returns the data pointer of a `Box` containing a clone of the concrete value.
+        // The concrete type is the same, so the metadata of the original is valid for the clone.
+        Some(unsafe { Box::from_raw(ptr::from_raw_parts_mut(cloner.clone_to_raw(), metadata)) })
+      }
+    }
+  };
+}
+
+implement_clone_with_markers!();
+implement_clone_with_markers!(Send);
+implement_clone_with_markers!(Send + Sync);
+
+/// A `Box<dyn TraitcastableAny>` (or one of its `Send`/`Sync` variants) that is guaranteed to be clonable.
+///
+/// Allows implementing `Clone` on types containing trait castable objects.
+pub struct ClonableBox<T: ?Sized = dyn TraitcastableAny>(Box<T>)
+where
+  Box<T>: TraitcastableCloneExt;
+
+impl<T: ?Sized> ClonableBox<T>
+where
+  Box<T>: TraitcastableCloneExt,
+{
+  /// Wraps the box if its concrete type is clonable.
+  ///
+  /// # Errors
+  /// In case the concrete type is not clonable the original box is returned as the error type.
+  pub fn try_new(inner: Box<T>) -> Result<Self, Box<T>> {
+    if inner.is_clonable() {
+      Ok(Self(inner))
+    } else {
+      Err(inner)
+    }
+  }
+
+  /// Returns the wrapped box.
+  #[must_use]
+  pub fn into_inner(self) -> Box<T> {
+    self.0
+  }
+}
+
+impl<T: ?Sized> Clone for ClonableBox<T>
+where
+  Box<T>: TraitcastableCloneExt,
+{
+  fn clone(&self) -> Self {
+    Self(
+      self
+        .0
+        .try_clone_box()
+        .expect("the concrete type was checked to be clonable"),
+    )
+  }
+}
+
+impl<T: ?Sized> Deref for ClonableBox<T>
+where
+  Box<T>: TraitcastableCloneExt,
+{
+  type Target = T;
+
+  fn deref(&self) -> &T {
+    &self.0
+  }
+}
+
+impl<T: ?Sized> DerefMut for ClonableBox<T>
+where
+  Box<T>: TraitcastableCloneExt,
+{
+  fn deref_mut(&mut self) -> &mut T {
+    &mut self.0
+  }
+}
+
+impl<T: ?Sized + Debug> Debug for ClonableBox<T>
+where
+  Box<T>: TraitcastableCloneExt,
+{
+  fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+    Debug::fmt(&*self.0, f)
+  }
+}
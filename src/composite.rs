@@ -0,0 +1,257 @@
+use core::{
+  any::TypeId,
+  fmt::{self, Debug, Formatter},
+};
+
+use alloc::{boxed::Box, vec::Vec};
+
+use crate::{Request, TraitcastTarget, TraitcastableAny, TraitcastableAnyInfra};
+
+/// Decides which component of a `Composite` provides a trait, that several of its components can be cast to.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub enum ConflictPolicy {
+  /// The component that was added first provides the trait.
+  #[default]
+  First,
+  /// The component that was added last provides the trait.
+  Last,
+}
+
+/// A handle to a component of a `Composite`.
+///
+/// Handles of removed components are never reused.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct ComponentKey(u64);
+
+/// An object assembled at runtime from boxed components, that can be cast to every trait any component can be cast to.
+///
+/// The `traitcast_targets` of the composite are the merged targets of its components.
+/// Casts resolve to the component providing the target, so `downcast_ref::<dyn Trait>` returns a reference into it.
+/// The guards of the targets (see `traitcast_allowed`) are the ones of the providing components.
+/// If several components can be cast to the same trait, the `ConflictPolicy` decides which one provides it.
+///
+/// Components can be added and removed at any time, the merged targets are rebuilt on every change.
+/// Scoped targets of the components (see `downcast_ref_scoped`) are not merged.
+/// Requests for values supplied by `provide` are forwarded to the components in the order they were added.
+/// Casts of owning pointers (like `Box<dyn TraitcastableAny>`) to the traits of the components are impossible,
+///  since the pointer owns the composite and not the component.
+pub struct Composite {
+  components: Vec<(ComponentKey, Box<dyn TraitcastableAny>)>,
+  policy: ConflictPolicy,
+  /// The merged targets of all components.
+  targets: Vec<TraitcastTarget>,
+  /// The index of the component providing the target with the same index.
+  providers: Vec<usize>,
+  next_key: u64,
+}
+
+impl Default for Composite {
+  fn default() -> Self {
+    Self::new()
+  }
+}
+
+impl Debug for Composite {
+  fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+    f.debug_struct("Composite")
+      .field(
+        "components",
+        &self
+          .components
+          .iter()
+          .map(|(_, component)| component)
+          .collect::<Vec<_>>(),
+      )
+      .field("policy", &self.policy)
+      .finish_non_exhaustive()
+  }
+}
+
+impl Composite {
+  /// Creates a composite without components, in which the first added component provides conflicting traits.
+  #[must_use]
+  pub const fn new() -> Self {
+    Self::with_policy(ConflictPolicy::First)
+  }
+
+  /// Creates a composite without components, which resolves conflicting traits with the policy.
+  #[must_use]
+  pub const fn with_policy(policy: ConflictPolicy) -> Self {
+    Self {
+      components: Vec::new(),
+      policy,
+      targets: Vec::new(),
+      providers: Vec::new(),
+      next_key: 0,
+    }
+  }
+
+  /// Returns the policy resolving conflicting traits.
+  #[must_use]
+  pub const fn policy(&self) -> ConflictPolicy {
+    self.policy
+  }
+
+  /// Returns the number of components.
+  #[must_use]
+  pub fn len(&self) -> usize {
+    self.components.len()
+  }
+
+  /// Returns true if the composite has no components.
+  #[must_use]
+  pub fn is_empty(&self) -> bool {
+    self.components.is_empty()
+  }
+
+  /// Adds the component and returns its handle.
+  pub fn add(&mut self, component: Box<dyn TraitcastableAny>) -> ComponentKey {
+    let key = ComponentKey(self.next_key);
+    self.next_key += 1;
+    self.components.push((key, component));
+    self.rebuild();
+    key
+  }
+
+  /// Removes the component with the handle and returns it.
+  ///
+  /// Traits it provided are provided by the remaining components, if possible.
+  /// Returns `None` if the component was already removed.
+  pub fn remove(&mut self, key: ComponentKey) -> Option<Box<dyn TraitcastableAny>> {
+    let index = self.index_of(key)?;
+    let (_, component) = self.components.remove(index);
+    self.rebuild();
+    Some(component)
+  }
+
+  /// Returns true if the composite contains the component with the handle.
+  #[must_use]
+  pub fn contains(&self, key: ComponentKey) -> bool {
+    self.index_of(key).is_some()
+  }
+
+  /// Returns the component with the handle.
+  #[must_use]
+  pub fn component(&self, key: ComponentKey) -> Option<&dyn TraitcastableAny> {
+    let index = self.index_of(key)?;
+    Some(self.components[index].1.as_ref())
+  }
+
+  /// Returns the component with the handle mutably.
+  #[must_use]
+  pub fn component_mut(&mut self, key: ComponentKey) -> Option<&mut dyn TraitcastableAny> {
+    let index = self.index_of(key)?;
+    Some(self.components[index].1.as_mut())
+  }
+
+  /// Iterates over the components in the order they were added.
+  pub fn components(&self) -> impl Iterator<Item = (ComponentKey, &dyn TraitcastableAny)> {
+    self
+      .components
+      .iter()
+      .map(|(key, component)| (*key, component.as_ref()))
+  }
+
+  /// Returns the first component of the concrete type `T`.
+  #[must_use]
+  pub fn get<T: TraitcastableAny>(&self) -> Option<&T> {
+    self
+      .components
+      .iter()
+      .find_map(|(_, component)| component.as_ref().downcast_ref())
+  }
+
+  /// Returns the first component of the concrete type `T` mutably.
+  #[must_use]
+  pub fn get_mut<T: TraitcastableAny>(&mut self) -> Option<&mut T> {
+    self
+      .components
+      .iter_mut()
+      .find_map(|(_, component)| component.as_mut().downcast_mut())
+  }
+
+  /// Returns the handle of the component, that provides `Target`.
+  #[must_use]
+  pub fn provider<Target: ?Sized + 'static>(&self) -> Option<ComponentKey> {
+    let index = self.provider_index(TypeId::of::<Target>())?;
+    Some(self.components[index].0)
+  }
+
+  fn index_of(&self, key: ComponentKey) -> Option<usize> {
+    // The components are sorted by their handles, since handles are increasing.
+    self
+      .components
+      .binary_search_by_key(&key, |(key, _)| *key)
+      .ok()
+  }
+
+  fn provider_index(&self, target: TypeId) -> Option<usize> {
+    let position = self
+      .targets
+      .iter()
+      .position(|merged| merged.target_type_id() == target)?;
+    Some(self.providers[position])
+  }
+
+  /// Merges the targets of all components according to the policy.
+  fn rebuild(&mut self) {
+    self.targets.clear();
+    self.providers.clear();
+    for (index, (_, component)) in self.components.iter().enumerate() {
+      for target in TraitcastableAny::traitcast_targets(component.as_ref()) {
+        let existing = self
+          .targets
+          .iter()
+          .position(|merged| merged.target_type_id() == target.target_type_id());
+        if let Some(position) = existing {
+          if self.policy == ConflictPolicy::Last {
+            self.targets[position] = *target;
+            self.providers[position] = index;
+          }
+        } else {
+          self.targets.push(*target);
+          self.providers.push(index);
+        }
+      }
+    }
+  }
+}
+
+// SAFETY:
+// The targets are copies of the targets of the components, whose metadata always points to a `static`.
+// The addresses of the targets are the addresses of the components providing them,
+//  which live as long as the borrow of the composite.
+// Targets with no provider can't be found, so casts never resolve to the composite itself.
+unsafe impl TraitcastableAny for Composite {
+  fn traitcast_targets(&self) -> &[TraitcastTarget] {
+    &self.targets
+  }
+
+  fn provide<'a>(&'a self, request: &mut Request<'a>) {
+    for (_, component) in &self.components {
+      TraitcastableAny::provide(component.as_ref(), request);
+    }
+  }
+
+  fn traitcast_address(&self, target: TypeId) -> Option<*const ()> {
+    let index = self.provider_index(target)?;
+    Some(<dyn TraitcastableAny>::target_address(
+      self.components[index].1.as_ref(),
+      target,
+    ))
+  }
+
+  fn traitcast_address_mut(&mut self, target: TypeId) -> Option<*mut ()> {
+    let index = self.provider_index(target)?;
+    Some(<dyn TraitcastableAny>::target_address_mut(
+      self.components[index].1.as_mut(),
+      target,
+    ))
+  }
+
+  fn traitcast_allowed(&self, target: TypeId) -> bool {
+    self.provider_index(target).is_none_or(|index| {
+      TraitcastableAny::traitcast_allowed(self.components[index].1.as_ref(), target)
+    })
+  }
+}
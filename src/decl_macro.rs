@@ -1,28 +1,51 @@
 /// Declarative macro for automatic implementation of `TraitcastableAny` (and `TraitcastableTo`).
 /// Generally not for direct invocation, but rather used by the proc-macro `make_trait_castable`.
 ///
-/// Syntax: `<concrete struct/enum/union> => (<target traits>, ...)`
+/// Syntax: `<concrete struct/enum/union> => (<target traits>, ...) in <key> => (<scoped target traits>, ...) ...
+///  if <guard> => (<guarded target traits>, ...) ... expose <field> => (<field target traits>, ...) ... delegate <field>`
+///
+/// Targets prefixed with `#[stable]` can also be found by their `StableId` (see `TraitcastTarget::from_stable`).
+/// The targets after `in <key>` can only be cast to with a reference to the key (see `downcast_ref_scoped`).
+/// The targets after `if <guard>` can only be cast to while the guard, a `Fn(&Self) -> bool`, returns true
+///  (see `TraitcastableAny::traitcast_allowed`).
+/// The targets after `expose <field>` cast to the field instead of the value itself (see `TraitcastTarget::from_field`).
+/// After `delegate <field>` casts to targets the value doesn't have are forwarded to the field,
+///  whose type has to implement `TraitcastableAny`.
+/// Enums can delegate to the payload of the active variant with `delegate { <pattern> => <payload binding>, ... }`.
+/// Variants not matched by any pattern are not delegated.
 ///
 /// # Usage
 /// ```no_build
 /// make_trait_castable_decl! {
 ///     SrcStruct1 => (DstTrait1, DstTrait2),
-///     SrcStruct2 => (DstTrait3, DstTrait4),
+///     SrcStruct2 => (DstTrait3, #[stable] DstTrait4),
+///     SrcStruct3 => (DstTrait5) in auth::Key => (DstTrait6) if Self::is_unlocked => (DstTrait9),
+///     SrcStruct4 => (DstTrait7) expose inner => (DstTrait8) delegate other,
+///     SrcEnum => () delegate { SrcEnum::A(payload) => payload, SrcEnum::B(payload) => payload },
 /// }
 /// ```
 #[macro_export]
 macro_rules! make_trait_castable_decl {
-  ($($source:ty => ($($target:path),* $(,)?)),+$(,)?) => {
+  ($(
+    $source:ty => ($($(#[$attr:ident])? $target:path),* $(,)?)
+    $(in $key:ty => ($($scoped:path),* $(,)?))*
+    $(if $guard:expr => ($($(#[$guarded_attr:ident])? $guarded:path),* $(,)?))*
+    $(expose $field:tt => ($($exposed:path),* $(,)?))*
+    $(delegate $delegate:tt)?
+  ),+$(,)?) => {
     $(
       $(
-        impl $crate::TraitcastableTo<dyn $target> for $source {
-          const METADATA: ::core::ptr::DynMetadata<dyn $target> = {
-            let self_ptr: *const $source = ::core::ptr::null::<$source>();
-            let dyn_ptr: *const dyn $target = self_ptr as _;
-
-            dyn_ptr.to_raw_parts().1
-          };
-        }
+        $crate::__traitcastable_to!($source => $target);
+      )*
+      $(
+        $(
+          $crate::__traitcastable_to!($source => $scoped);
+        )*
+      )*
+      $(
+        $(
+          $crate::__traitcastable_to!($source => $guarded);
+        )*
       )*
       // Safety:
       // All returned `TraitcastTarget`s are valid for $source
@@ -33,21 +56,212 @@ macro_rules! make_trait_castable_decl {
             let a:&[()] = &[$({
               let _: &dyn $target;
               ()
-            },)*];
+            },)* $($({
+              let _: &dyn $guarded;
+              ()
+            },)*)* $($({
+              let _: &dyn $exposed;
+              ()
+            },)*)*];
             a.len()
           };
           const TARGETS: [$crate::TraitcastTarget; TARGETS_LEN] = {
             #[allow(unused_mut)]
             let mut targets : [$crate::TraitcastTarget; TARGETS_LEN] = [
               $(
-                $crate::TraitcastTarget::from::<$source, dyn $target>(),
+                $crate::__traitcast_target!($(#[$attr])? $source => $target),
+              )*
+              $(
+                $(
+                  $crate::__traitcast_target!($(#[$guarded_attr])? $source => $guarded),
+                )*
+              )*
+              $(
+                $(
+                  $crate::__traitcast_field_target!($source, $field => $exposed),
+                )*
               )*
             ];
             targets
           };
           &TARGETS
         }
+        fn traitcast_scoped_targets(&self) -> &[$crate::TraitcastTarget] {
+          #[allow(clippy::unused_unit)]
+          const SCOPED_TARGETS_LEN: usize = {
+            let a:&[()] = &[$($({
+              let _: &dyn $scoped;
+              ()
+            },)*)*];
+            a.len()
+          };
+          const SCOPED_TARGETS: [$crate::TraitcastTarget; SCOPED_TARGETS_LEN] = [
+            $(
+              $(
+                $crate::TraitcastTarget::from_scoped::<$source, dyn $scoped, $key>(),
+              )*
+            )*
+          ];
+          &SCOPED_TARGETS
+        }
+        #[allow(unused_variables)]
+        fn traitcast_allowed(&self, target: ::core::any::TypeId) -> bool {
+          $(
+            $(
+              if target == ::core::any::TypeId::of::<dyn $guarded>() {
+                return ($guard)(self);
+              }
+            )*
+          )*
+          $(
+            if !$crate::__has_own_target!(self, target) {
+              if let ::core::option::Option::Some(delegate) = $crate::__traitcast_delegate!(ref self, $delegate) {
+                return $crate::TraitcastableAny::traitcast_allowed(delegate, target);
+              }
+            }
+          )?
+          true
+        }
+        $(
+          fn find_traitcast_target(&self, target: ::core::any::TypeId) -> ::core::option::Option<&$crate::TraitcastTarget> {
+            <Self as $crate::TraitcastableAny>::traitcast_targets(self)
+              .iter()
+              .find(|possible| possible.target_type_id() == target)
+              .or_else(|| {
+                let delegate = $crate::__traitcast_delegate!(ref self, $delegate)?;
+                $crate::TraitcastableAny::find_traitcast_target(delegate, target)
+              })
+          }
+          fn traitcast_address(&self, target: ::core::any::TypeId) -> ::core::option::Option<*const ()> {
+            if $crate::__has_own_target!(self, target) {
+              return ::core::option::Option::None;
+            }
+            // Delegated targets never resolve to self, even if the delegate is stored at the address of self.
+            let delegate = $crate::__traitcast_delegate!(ref self, $delegate)?;
+            ::core::option::Option::Some(
+              $crate::TraitcastableAny::traitcast_address(delegate, target)
+                .unwrap_or_else(|| ::core::ptr::from_ref(delegate).cast::<()>()),
+            )
+          }
+          fn traitcast_address_mut(&mut self, target: ::core::any::TypeId) -> ::core::option::Option<*mut ()> {
+            if $crate::__has_own_target!(self, target) {
+              return ::core::option::Option::None;
+            }
+            let delegate = $crate::__traitcast_delegate!(mut self, $delegate)?;
+            let address = $crate::TraitcastableAny::traitcast_address_mut(delegate, target);
+            ::core::option::Option::Some(
+              address.unwrap_or_else(|| ::core::ptr::from_mut(delegate).cast::<()>()),
+            )
+          }
+          fn provide<'a>(&'a self, request: &mut $crate::Request<'a>) {
+            if let ::core::option::Option::Some(delegate) = $crate::__traitcast_delegate!(ref self, $delegate) {
+              $crate::TraitcastableAny::provide(delegate, request);
+            }
+          }
+        )?
       }
     )+
   };
 }
+
+/// Implements `TraitcastableTo` for a single target of `make_trait_castable_decl`.
+#[doc(hidden)]
+#[macro_export]
+macro_rules! __traitcastable_to {
+  ($source:ty => $target:path) => {
+    impl $crate::TraitcastableTo<dyn $target> for $source {
+      const METADATA: ::core::ptr::DynMetadata<dyn $target> = {
+        let self_ptr: *const $source = ::core::ptr::null::<$source>();
+        let dyn_ptr: *const dyn $target = self_ptr as _;
+
+        dyn_ptr.to_raw_parts().1
+      };
+    }
+  };
+}
+
+/// Creates the `TraitcastTarget` for a single target of a field of `make_trait_castable_decl`.
+#[doc(hidden)]
+#[macro_export]
+macro_rules! __traitcast_field_target {
+  ($source:ty, $field:tt => $target:path) => {{
+    const METADATA: ::core::ptr::DynMetadata<dyn $target> = {
+      let source = ::core::mem::MaybeUninit::<$source>::uninit();
+      // SAFETY:
+      // Only the address of the field is computed, the uninitialized value is never read.
+      let field = unsafe { &raw const (*source.as_ptr()).$field };
+      let dyn_ptr: *const dyn $target = field as _;
+
+      dyn_ptr.to_raw_parts().1
+    };
+    // SAFETY:
+    // The metadata was created from a pointer to the field at the offset.
+    unsafe {
+      $crate::TraitcastTarget::from_field::<dyn $target>(
+        ::core::mem::offset_of!($source, $field),
+        &METADATA,
+      )
+    }
+  }};
+}
+
+/// Returns the delegate of a value of `make_trait_castable_decl` as `Option<&dyn TraitcastableAny>` (or `&mut`).
+///
+/// The delegate is either a field or the payload bound by the matching pattern, like `{ Shape::Circle(payload) => payload }`.
+#[doc(hidden)]
+#[macro_export]
+macro_rules! __traitcast_delegate {
+  (ref $this:expr, { $($pattern:pat => $payload:ident),* $(,)? }) => {
+    match $this {
+      $($pattern => ::core::option::Option::Some($payload as &dyn $crate::TraitcastableAny),)*
+      #[allow(unreachable_patterns)]
+      _ => ::core::option::Option::None,
+    }
+  };
+  (mut $this:expr, { $($pattern:pat => $payload:ident),* $(,)? }) => {
+    match $this {
+      $($pattern => ::core::option::Option::Some($payload as &mut dyn $crate::TraitcastableAny),)*
+      #[allow(unreachable_patterns)]
+      _ => ::core::option::Option::None,
+    }
+  };
+  (ref $this:expr, $field:tt) => {
+    ::core::option::Option::Some(&$this.$field as &dyn $crate::TraitcastableAny)
+  };
+  (mut $this:expr, $field:tt) => {
+    ::core::option::Option::Some(&mut $this.$field as &mut dyn $crate::TraitcastableAny)
+  };
+}
+
+/// Returns true if the value itself (and not its delegate) has a target with the `TypeId`.
+///
+/// Scoped targets don't count, since `find_traitcast_target` falls back to the delegate for them.
+/// Scoped casts never consult `traitcast_address`, so they still resolve to the value itself.
+#[doc(hidden)]
+#[macro_export]
+macro_rules! __has_own_target {
+  ($this:expr, $target:expr) => {
+    <Self as $crate::TraitcastableAny>::traitcast_targets($this)
+      .iter()
+      .any(|possible| possible.target_type_id() == $target)
+  };
+}
+
+/// Creates the `TraitcastTarget` for a single target of `make_trait_castable_decl`.
+#[doc(hidden)]
+#[macro_export]
+macro_rules! __traitcast_target {
+  (#[stable] $source:ty => $target:path) => {
+    $crate::TraitcastTarget::from_stable::<$source, dyn $target>()
+  };
+  (#[$attr:ident] $source:ty => $target:path) => {
+    ::core::compile_error!(::core::concat!(
+      "unknown traitcast target attribute `",
+      ::core::stringify!($attr),
+      "`"
+    ))
+  };
+  ($source:ty => $target:path) => {
+    $crate::TraitcastTarget::from::<$source, dyn $target>()
+  };
+}
@@ -0,0 +1,144 @@
+use core::{
+  any::{Any, TypeId},
+  cmp::Reverse,
+  fmt::{self, Debug, Formatter},
+  iter,
+};
+
+use alloc::{boxed::Box, collections::BTreeMap, vec::Vec};
+
+use crate::{TraitcastTarget, TraitcastableAny, TraitcastableAnyInfra};
+
+type PairHandler<R> = Box<dyn Fn(&dyn TraitcastableAny, &dyn TraitcastableAny) -> Option<R>>;
+
+/// Double dispatch over pairs of trait castable objects.
+///
+/// Handlers are registered for pairs of trait objects or concrete types, like `(dyn Flammable, dyn Fire)`
+///  or `(Torch, dyn Fire)`, and looked up through the concrete types and `traitcast_targets` of both objects.
+///
+/// Matching is symmetric: A handler for `(A, B)` also handles the pair `(b, a)`, with the arguments swapped back.
+/// If several handlers match, the most specific one wins:
+/// 1. The handler with more concrete types in its pair.
+/// 2. The handler registered first.
+/// 3. The handler matching without swapping the arguments.
+///
+/// Only the targets listed in `traitcast_targets` are matched, targets whose guard rejects the cast are skipped.
+/// Targets reached through a delegate or provided values are never matched, since they can't be enumerated.
+pub struct DispatchTable<R = ()> {
+  handlers: Vec<PairHandler<R>>,
+  /// The index of the first handler registered for the pair of `TypeId`s. Later ones for the same pair never win.
+  pairs: BTreeMap<(TypeId, TypeId), usize>,
+}
+
+impl<R> Default for DispatchTable<R> {
+  fn default() -> Self {
+    Self::new()
+  }
+}
+
+impl<R> Debug for DispatchTable<R> {
+  fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+    f.debug_struct("DispatchTable")
+      .field("handlers", &self.handlers.len())
+      .finish_non_exhaustive()
+  }
+}
+
+/// Returns the `TypeId`s of the concrete type and of the cast targets of the object, whose guards allow the cast.
+fn dispatch_type_ids(object: &dyn TraitcastableAny) -> impl Iterator<Item = TypeId> + '_ {
+  let any: &dyn Any = object;
+  iter::once(any.type_id()).chain(
+    object
+      .traitcast_targets()
+      .iter()
+      .map(TraitcastTarget::target_type_id)
+      .filter(|&target| TraitcastableAny::traitcast_allowed(object, target)),
+  )
+}
+
+impl<R> DispatchTable<R> {
+  /// Creates a table without handlers.
+  #[must_use]
+  pub const fn new() -> Self {
+    Self {
+      handlers: Vec::new(),
+      pairs: BTreeMap::new(),
+    }
+  }
+
+  /// Returns the number of registered handlers.
+  #[must_use]
+  pub fn len(&self) -> usize {
+    self.handlers.len()
+  }
+
+  /// Returns true if no handlers are registered.
+  #[must_use]
+  pub fn is_empty(&self) -> bool {
+    self.handlers.is_empty()
+  }
+
+  /// Registers a handler for pairs of objects castable to `A` and `B`, in any order.
+  ///
+  /// `A` and `B` can be trait objects or concrete types.
+  pub fn register<A: ?Sized + 'static, B: ?Sized + 'static>(
+    &mut self,
+    handler: impl Fn(&A, &B) -> R + 'static,
+  ) -> &mut Self {
+    self
+      .pairs
+      .entry((TypeId::of::<A>(), TypeId::of::<B>()))
+      .or_insert(self.handlers.len());
+    self.handlers.push(Box::new(move |a, b| {
+      Some(handler(a.downcast_ref()?, b.downcast_ref()?))
+    }));
+    self
+  }
+
+  /// Finds the most specific handler for the pair.
+  ///
+  /// Returns the index of the handler and whether the arguments have to be swapped.
+  fn find(&self, a: &dyn TraitcastableAny, b: &dyn TraitcastableAny) -> Option<(usize, bool)> {
+    let (a_any, b_any): (&dyn Any, &dyn Any) = (a, b);
+    let (a_concrete, b_concrete) = (a_any.type_id(), b_any.type_id());
+
+    let mut best = None;
+    for a_type_id in dispatch_type_ids(a) {
+      for b_type_id in dispatch_type_ids(b) {
+        let specificity = u8::from(a_type_id == a_concrete) + u8::from(b_type_id == b_concrete);
+        for (swapped, key) in [
+          (false, (a_type_id, b_type_id)),
+          (true, (b_type_id, a_type_id)),
+        ] {
+          let Some(&index) = self.pairs.get(&key) else {
+            continue;
+          };
+          let rank = (specificity, Reverse(index), !swapped);
+          if best.is_none_or(|(best_rank, _)| rank > best_rank) {
+            best = Some((rank, (index, swapped)));
+          }
+        }
+      }
+    }
+    best.map(|(_, found)| found)
+  }
+
+  /// Returns true if a handler matches the pair.
+  #[must_use]
+  pub fn can_dispatch(&self, a: &dyn TraitcastableAny, b: &dyn TraitcastableAny) -> bool {
+    self.find(a, b).is_some()
+  }
+
+  /// Calls the most specific handler for the pair and returns its result.
+  ///
+  /// Returns `None` if no handler matches.
+  pub fn dispatch(&self, a: &dyn TraitcastableAny, b: &dyn TraitcastableAny) -> Option<R> {
+    let (index, swapped) = self.find(a, b)?;
+    let handler = &self.handlers[index];
+    if swapped {
+      handler(b, a)
+    } else {
+      handler(a, b)
+    }
+  }
+}
@@ -0,0 +1,122 @@
+use core::{
+  any::Any,
+  cmp::Ordering,
+  hash::{Hash, Hasher},
+  ops::Deref,
+  ptr,
+};
+
+use crate::{AsTraitcastableAny, TraitcastableAny, TraitcastableAnyInfra};
+
+/// Cast target comparing the concrete type for equality.
+///
+/// List it as a target (or `PartialEq` when using the `make_trait_castable` attribute macro) together with `TraitcastableEq`
+///  to make `DynKey` compare the values. Without `TraitcastableEq` `DynKey` only compares the identity of the values.
+pub trait TraitcastablePartialEq {
+  /// Returns true if `other` has the same concrete type and is equal to `self`.
+  fn dyn_eq(&self, other: &dyn TraitcastableAny) -> bool;
+}
+
+impl<T: PartialEq + 'static> TraitcastablePartialEq for T {
+  fn dyn_eq(&self, other: &dyn TraitcastableAny) -> bool {
+    <dyn Any>::downcast_ref::<Self>(other).is_some_and(|other| self == other)
+  }
+}
+
+/// Cast target marking the equality of the concrete type as an equivalence relation.
+///
+/// List it as a target (or `Eq` when using the `make_trait_castable` attribute macro) to make `DynKey` compare the values.
+pub trait TraitcastableEq: TraitcastablePartialEq {}
+
+impl<T: Eq + 'static> TraitcastableEq for T {}
+
+/// Cast target hashing the concrete type.
+///
+/// List it as a target (or `Hash` when using the `make_trait_castable` attribute macro) to make `DynKey` hash the values.
+pub trait TraitcastableHash {
+  /// Feeds the value into the given `Hasher`.
+  fn dyn_hash(&self, state: &mut dyn Hasher);
+}
+
+impl<T: Hash> TraitcastableHash for T {
+  fn dyn_hash(&self, mut state: &mut dyn Hasher) {
+    self.hash(&mut state);
+  }
+}
+
+/// Cast target ordering the concrete type.
+///
+/// List it as a target (or `PartialOrd` when using the `make_trait_castable` attribute macro) to make `DynKey` order the values.
+pub trait TraitcastablePartialOrd {
+  /// Returns the ordering between `self` and `other` if `other` has the same concrete type.
+  fn dyn_partial_cmp(&self, other: &dyn TraitcastableAny) -> Option<Ordering>;
+}
+
+impl<T: PartialOrd + 'static> TraitcastablePartialOrd for T {
+  fn dyn_partial_cmp(&self, other: &dyn TraitcastableAny) -> Option<Ordering> {
+    <dyn Any>::downcast_ref::<Self>(other).and_then(|other| self.partial_cmp(other))
+  }
+}
+
+/// A wrapper around a pointer to a `dyn TraitcastableAny` implementing `PartialEq`, `Eq`, `Hash` and `PartialOrd`.
+///
+/// Allows using trait castable objects as keys, for example in a `HashMap<DynKey<Box<dyn TraitcastableAny>>, _>`.
+///
+/// Values of different concrete types are never equal and are ordered by their `TypeId`.
+/// Values of the same concrete type are compared using the `TraitcastableEq`, `TraitcastableHash`
+///  and `TraitcastablePartialOrd` targets. Values without the `TraitcastableEq` target are only equal to themselves
+///  (compared by address), so equality is an equivalence relation even for types like `f64`.
+/// Values without the other targets are not hashed beyond their `TypeId` and are unordered.
+/// Only targets of the concrete type itself are used, targets reached through a delegate, a field or `provide` are ignored.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct DynKey<P>(pub P);
+
+impl<P: Deref<Target: AsTraitcastableAny>> DynKey<P> {
+  fn castable(&self) -> &dyn TraitcastableAny {
+    AsTraitcastableAny::as_traitcastable_any(&*self.0)
+  }
+}
+
+impl<P: Deref<Target: AsTraitcastableAny>> PartialEq for DynKey<P> {
+  fn eq(&self, other: &Self) -> bool {
+    let (lhs, rhs) = (self.castable(), other.castable());
+    if TraitcastableAny::type_id(lhs) != TraitcastableAny::type_id(rhs) {
+      return false;
+    }
+    // Only `Eq` guarantees reflexivity, other values fall back to their identity.
+    match TraitcastableAnyInfra::<dyn TraitcastableEq>::downcast_ref_in_place(lhs) {
+      Some(lhs) => lhs.dyn_eq(rhs),
+      None => ptr::addr_eq(lhs, rhs),
+    }
+  }
+}
+
+impl<P: Deref<Target: AsTraitcastableAny>> Eq for DynKey<P> {}
+
+impl<P: Deref<Target: AsTraitcastableAny>> Hash for DynKey<P> {
+  fn hash<H: Hasher>(&self, state: &mut H) {
+    let castable = self.castable();
+    TraitcastableAny::type_id(castable).hash(state);
+    if let Some(hashable) =
+      TraitcastableAnyInfra::<dyn TraitcastableHash>::downcast_ref_in_place(castable)
+    {
+      hashable.dyn_hash(state);
+    }
+  }
+}
+
+impl<P: Deref<Target: AsTraitcastableAny>> PartialOrd for DynKey<P> {
+  fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+    let (lhs, rhs) = (self.castable(), other.castable());
+    match TraitcastableAny::type_id(lhs).cmp(&TraitcastableAny::type_id(rhs)) {
+      Ordering::Equal if self == other => Some(Ordering::Equal),
+      // Values, that are not equal, must not be ordered as equal.
+      Ordering::Equal => {
+        TraitcastableAnyInfra::<dyn TraitcastablePartialOrd>::downcast_ref_in_place(lhs)
+          .and_then(|lhs| lhs.dyn_partial_cmp(rhs))
+          .filter(|ordering| *ordering != Ordering::Equal)
+      },
+      ordering => Some(ordering),
+    }
+  }
+}
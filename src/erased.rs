@@ -0,0 +1,228 @@
+use core::{any::TypeId, marker::PhantomData, ptr};
+
+use crate::{StableId, StableTypeId, TraitcastTarget, TraitcastableAny};
+
+/// The result of a traitcast lookup by `TypeId`, which is not yet bound to a Rust type.
+///
+/// Holds the data pointer of the value the target casts to and the `TraitcastTarget` that was found.
+/// Use `downcast` to turn it into a `&dyn Trait`.
+#[derive(Clone, Copy)]
+pub struct ErasedTraitRef<'a> {
+  data: *const (),
+  target: &'a TraitcastTarget,
+  _marker: PhantomData<&'a ()>,
+}
+
+impl<'a> ErasedTraitRef<'a> {
+  /// Returns the `TraitcastTarget` that was found by the lookup.
+  #[must_use]
+  pub const fn target(&self) -> &'a TraitcastTarget {
+    self.target
+  }
+
+  /// Returns a reference to the object as `Target`, or `None` if the lookup was not for `Target`.
+  #[must_use]
+  pub fn downcast<Target: ?Sized + 'static>(self) -> Option<&'a Target> {
+    let metadata = self.target.metadata::<Target>()?;
+    // SAFETY:
+    // The metadata was found by the object the data pointer originates from.
+    Some(unsafe { &*ptr::from_raw_parts(self.data, metadata) })
+  }
+
+  /// Returns a reference to the object as `Target`, or `None` if the lookup was not for the `StableId` of `Target`.
+  ///
+  /// # Safety
+  /// See `TraitcastTarget::metadata_stable`.
+  #[must_use]
+  pub unsafe fn downcast_stable<Target: StableTypeId + ?Sized>(self) -> Option<&'a Target> {
+    // SAFETY: Guaranteed by the caller.
+    let metadata = unsafe { self.target.metadata_stable::<Target>() }?;
+    // SAFETY:
+    // The metadata was found by the object the data pointer originates from.
+    Some(unsafe { &*ptr::from_raw_parts(self.data, metadata) })
+  }
+}
+
+/// The result of a mutable traitcast lookup by `TypeId`, which is not yet bound to a Rust type.
+///
+/// Holds the data pointer of the value the target casts to and the `TraitcastTarget` that was found.
+/// Use `downcast` to turn it into a `&mut dyn Trait`.
+pub struct ErasedTraitMut<'a> {
+  data: *mut (),
+  target: &'a TraitcastTarget,
+  _marker: PhantomData<&'a mut ()>,
+}
+
+impl<'a> ErasedTraitMut<'a> {
+  /// Returns the `TraitcastTarget` that was found by the lookup.
+  #[must_use]
+  pub const fn target(&self) -> &'a TraitcastTarget {
+    self.target
+  }
+
+  /// Returns a mutable reference to the object as `Target`.
+  ///
+  /// # Errors
+  /// In case the lookup was not for `Target` the original input is returned as the error type.
+  pub fn downcast<Target: ?Sized + 'static>(self) -> Result<&'a mut Target, Self> {
+    let Some(metadata) = self.target.metadata::<Target>() else {
+      return Err(self);
+    };
+    // SAFETY:
+    // The metadata was found by the object the data pointer originates from,
+    //  which is exclusively borrowed for `'a`.
+    Ok(unsafe { &mut *ptr::from_raw_parts_mut(self.data, metadata) })
+  }
+
+  /// Returns a mutable reference to the object as `Target`.
+  ///
+  /// # Errors
+  /// In case the lookup was not for the `StableId` of `Target` the original input is returned as the error type.
+  ///
+  /// # Safety
+  /// See `TraitcastTarget::metadata_stable`.
+  pub unsafe fn downcast_stable<Target: StableTypeId + ?Sized>(
+    self,
+  ) -> Result<&'a mut Target, Self> {
+    // SAFETY: Guaranteed by the caller.
+    let Some(metadata) = (unsafe { self.target.metadata_stable::<Target>() }) else {
+      return Err(self);
+    };
+    // SAFETY:
+    // The metadata was found by the object the data pointer originates from,
+    //  which is exclusively borrowed for `'a`.
+    Ok(unsafe { &mut *ptr::from_raw_parts_mut(self.data, metadata) })
+  }
+}
+
+fn find_target_by_name<'a>(
+  targets: &'a [TraitcastTarget],
+  name: &str,
+) -> Option<&'a TraitcastTarget> {
+  targets
+    .iter()
+    .find(|target| target.target_type_name() == name || target.target_path_name() == name)
+    .or_else(|| targets.iter().find(|target| target.matches_name(name)))
+}
+
+fn find_target_by_stable_id(targets: &[TraitcastTarget], id: StableId) -> Option<&TraitcastTarget> {
+  targets
+    .iter()
+    .find(|target| target.target_stable_id() == Some(id))
+}
+
+macro_rules! implement_erased_with_markers {
+  ($($(+)? $traits:ident)*) => {
+    impl dyn TraitcastableAny $(+ $traits)* {
+      /// Looks up the `TraitcastTarget` for the type with the `TypeId` without binding the result to a Rust type.
+      ///
+      /// Returns `None` if a traitcast to the type is not possible.
+      #[must_use]
+      pub fn cast_erased(&self, target: TypeId) -> Option<ErasedTraitRef<'_>> {
+        let target = self
+          .find_traitcast_target(target)
+          .filter(|_| self.traitcast_allowed(target))?;
+        Some(ErasedTraitRef {
+          data: <dyn TraitcastableAny>::target_address(self, target.target_type_id()).wrapping_byte_add(target.offset()),
+          target,
+          _marker: PhantomData,
+        })
+      }
+
+      /// Looks up the `TraitcastTarget` for the trait with the name without binding the result to a Rust type.
+      ///
+      /// The name can be the full `type_name` of the trait object, the path of the trait or just the name of the trait.
+      /// See `TraitcastTarget::matches_name`. Exact matches of the full name or path are preferred over short names.
+      ///
+      /// Only the names of the listed targets (see `target_names`) are searched, targets reached through a delegate
+      ///  or provided values can't be found by name, since they can't be enumerated.
+      /// The target with the name is then cast to like `cast_erased` does.
+      ///
+      /// Returns `None` if no target has the name or its guard rejects the cast.
+      #[must_use]
+      pub fn find_target_by_name(&self, name: &str) -> Option<ErasedTraitRef<'_>> {
+        let type_id = find_target_by_name(self.traitcast_targets(), name)?.target_type_id();
+        self.cast_erased(type_id)
+      }
+
+      /// Mutable variant of `find_target_by_name`.
+      #[must_use]
+      pub fn find_target_by_name_mut(&mut self, name: &str) -> Option<ErasedTraitMut<'_>> {
+        let type_id = find_target_by_name(TraitcastableAny::traitcast_targets(&*self), name)?.target_type_id();
+        self.cast_erased_mut(type_id)
+      }
+
+      /// Looks up the `TraitcastTarget` for the trait with the `StableId` without binding the result to a Rust type.
+      ///
+      /// Unlike `cast_erased` this also finds targets of objects created by separately compiled artifacts (like plugins),
+      ///  whose `TypeId`s are not guaranteed to match. Only targets created with `TraitcastTarget::from_stable` are found.
+      /// Targets reached through a delegate or provided values are not found, since they can't be enumerated.
+      ///
+      /// Returns `None` if a traitcast to the trait is not possible or its guard rejects the cast.
+      #[must_use]
+      pub fn cast_stable(&self, target: StableId) -> Option<ErasedTraitRef<'_>> {
+        let type_id = find_target_by_stable_id(self.traitcast_targets(), target)?.target_type_id();
+        self.cast_erased(type_id)
+      }
+
+      /// Mutable variant of `cast_stable`.
+      #[must_use]
+      pub fn cast_stable_mut(&mut self, target: StableId) -> Option<ErasedTraitMut<'_>> {
+        let type_id = find_target_by_stable_id(TraitcastableAny::traitcast_targets(&*self), target)?.target_type_id();
+        self.cast_erased_mut(type_id)
+      }
+
+      /// Returns some reference to the inner value if it is castable to `Target` by its `StableId`, or `None` if it isn’t.
+      ///
+      /// # Safety
+      /// See `TraitcastTarget::metadata_stable`.
+      #[must_use]
+      pub unsafe fn downcast_ref_stable<Target: StableTypeId + ?Sized>(&self) -> Option<&Target> {
+        // SAFETY: Guaranteed by the caller.
+        unsafe { self.cast_stable(Target::STABLE_ID)?.downcast_stable() }
+      }
+
+      /// Returns some mutable reference to the inner value if it is castable to `Target` by its `StableId`, or `None` if it isn’t.
+      ///
+      /// # Safety
+      /// See `TraitcastTarget::metadata_stable`.
+      #[must_use]
+      pub unsafe fn downcast_mut_stable<Target: StableTypeId + ?Sized>(
+        &mut self,
+      ) -> Option<&mut Target> {
+        // SAFETY: Guaranteed by the caller.
+        unsafe { self.cast_stable_mut(Target::STABLE_ID)?.downcast_stable().ok() }
+      }
+
+      /// Returns the `type_name`s of all traits to which can be cast.
+      ///
+      /// Can be used to list the available targets, for example in error messages.
+      /// Targets reached through a delegate and provided values are not listed.
+      pub fn target_names(&self) -> impl Iterator<Item = &'static str> + '_ {
+        self
+          .traitcast_targets()
+          .iter()
+          .map(TraitcastTarget::target_type_name)
+      }
+
+      /// Mutable variant of `cast_erased`.
+      #[must_use]
+      pub fn cast_erased_mut(&mut self, target: TypeId) -> Option<ErasedTraitMut<'_>> {
+        if !TraitcastableAny::traitcast_allowed(&*self, target) {
+          return None;
+        }
+        let data = <dyn TraitcastableAny>::target_address_mut(self, target);
+        let target = TraitcastableAny::find_traitcast_target(&*self, target)?;
+        Some(ErasedTraitMut {
+          data: data.wrapping_byte_add(target.offset()),
+          target,
+          _marker: PhantomData,
+        })
+      }
+    }
+  };
+}
+
+implement_erased_with_markers!();
+implement_erased_with_markers!(Send);
+implement_erased_with_markers!(Send + Sync);
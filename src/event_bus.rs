@@ -0,0 +1,145 @@
+use core::{
+  any::{Any, TypeId},
+  fmt::{self, Debug, Formatter},
+  iter,
+};
+
+use alloc::{
+  boxed::Box,
+  collections::{BTreeMap, VecDeque},
+  vec::Vec,
+};
+
+use crate::{TraitcastTarget, TraitcastableAny, TraitcastableAnyInfra};
+
+/// An event, that can be queued in an `EventBus`.
+pub type Event = Box<dyn TraitcastableAny + Send>;
+
+/// Calls the subscribed handler if the event can be cast to its target and returns whether it did.
+type Handler = Box<dyn FnMut(&dyn TraitcastableAny) -> bool + Send>;
+
+/// Identifies a subscription of an `EventBus`, so it can be removed again.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct SubscriptionId(u64);
+
+/// Delivers events to the handlers subscribed to any trait (or concrete type) the event can be cast to.
+///
+/// Subscriptions are keyed by the `TypeId` of their target.
+/// The matching subscriptions of an event are found through its concrete type and its `traitcast_targets`.
+/// So publishing an event only visits the subscriptions of its own targets.
+/// Targets reached through a delegate or provided values don't match any subscriptions, since they can't be enumerated.
+///
+/// Events can be dispatched synchronously with `publish` or be queued with `enqueue` and dispatched later with `drain`.
+#[derive(Default)]
+pub struct EventBus {
+  subscriptions: BTreeMap<TypeId, Vec<(SubscriptionId, Handler)>>,
+  queue: VecDeque<Event>,
+  next_id: u64,
+}
+
+impl Debug for EventBus {
+  fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+    f.debug_struct("EventBus")
+      .field(
+        "subscriptions",
+        &self.subscriptions.values().map(Vec::len).sum::<usize>(),
+      )
+      .field("queued", &self.queue.len())
+      .finish_non_exhaustive()
+  }
+}
+
+impl EventBus {
+  /// Creates a bus without subscriptions.
+  #[must_use]
+  pub const fn new() -> Self {
+    Self {
+      subscriptions: BTreeMap::new(),
+      queue: VecDeque::new(),
+      next_id: 0,
+    }
+  }
+
+  /// Subscribes the handler to all events, that can be cast to `Target`.
+  pub fn subscribe<Target: ?Sized + 'static>(
+    &mut self,
+    mut handler: impl FnMut(&Target) + Send + 'static,
+  ) -> SubscriptionId {
+    let id = SubscriptionId(self.next_id);
+    self.next_id += 1;
+    let handler: Handler = Box::new(move |event: &dyn TraitcastableAny| {
+      event.downcast_ref().map(&mut handler).is_some()
+    });
+    self
+      .subscriptions
+      .entry(TypeId::of::<Target>())
+      .or_default()
+      .push((id, handler));
+    id
+  }
+
+  /// Removes the subscription.
+  ///
+  /// Returns false if it was already removed.
+  pub fn unsubscribe(&mut self, id: SubscriptionId) -> bool {
+    let Some((&type_id, handlers)) = self
+      .subscriptions
+      .iter_mut()
+      .find(|(_, handlers)| handlers.iter().any(|(other, _)| *other == id))
+    else {
+      return false;
+    };
+    handlers.retain(|(other, _)| *other != id);
+    if handlers.is_empty() {
+      self.subscriptions.remove(&type_id);
+    }
+    true
+  }
+
+  /// Delivers the event to all matching handlers and returns how many of them were called.
+  ///
+  /// A handler is skipped if the guard of its target rejects the cast in the current state of the event.
+  ///
+  /// The handlers subscribed to the concrete type are called first,
+  ///  followed by the ones of each cast target in the order of `traitcast_targets`.
+  /// Handlers of the same target are called in the order of their subscription.
+  pub fn publish(&mut self, event: &dyn TraitcastableAny) -> usize {
+    let any: &dyn Any = event;
+    let concrete = iter::once(any.type_id());
+    let targets = event
+      .traitcast_targets()
+      .iter()
+      .map(TraitcastTarget::target_type_id);
+    let mut delivered = 0;
+    for type_id in concrete.chain(targets) {
+      if let Some(handlers) = self.subscriptions.get_mut(&type_id) {
+        for (_, handler) in handlers {
+          if handler(event) {
+            delivered += 1;
+          }
+        }
+      }
+    }
+    delivered
+  }
+
+  /// Queues the event to be delivered by `drain`.
+  pub fn enqueue(&mut self, event: Event) {
+    self.queue.push_back(event);
+  }
+
+  /// Returns the number of queued events.
+  #[must_use]
+  pub fn queued(&self) -> usize {
+    self.queue.len()
+  }
+
+  /// Delivers all queued events in the order they were queued and returns how many handlers were called.
+  pub fn drain(&mut self) -> usize {
+    let mut delivered = 0;
+    while let Some(event) = self.queue.pop_front() {
+      delivered += self.publish(event.as_ref());
+    }
+    delivered
+  }
+}
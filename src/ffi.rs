@@ -0,0 +1,184 @@
+//! Opaque reference counted handles for C and C++ code, similar to COM's `QueryInterface`.
+//!
+//! C code holds a `TraitcastHandle` as an opaque pointer and asks for interfaces by their raw `StableId`.
+//! Interfaces are cast targets marked with `#[stable]` (see `TraitcastTarget::from_stable`).
+//! The declarations of the functions are in `include/trait_cast.h`.
+//!
+//! The functions taking typed handles (like `greeter_greet(const TraitcastHandle *greeter)`) are written in Rust
+//!  and use `TraitcastHandle::from_raw` and `TraitcastHandle::downcast_interface`.
+
+use core::ptr;
+
+use alloc::sync::Arc;
+
+use crate::{StableId, StableTypeId, TraitcastableAny};
+
+/// A reference counted handle to a trait castable object, which C code holds as an opaque pointer.
+///
+/// The handle either refers to the object itself or to one of its interfaces.
+/// Every handle has its own reference count, but shares the object with the handles it was queried from.
+pub struct TraitcastHandle {
+  object: Arc<dyn TraitcastableAny + Send + Sync>,
+  /// `None` for handles to the object itself.
+  interface: Option<StableId>,
+}
+
+impl TraitcastHandle {
+  /// Creates a handle to the object with a reference count of one and returns it as a pointer for C code.
+  ///
+  /// The handle must be released with `trait_cast_handle_release`.
+  #[must_use]
+  pub fn into_raw(object: Arc<dyn TraitcastableAny + Send + Sync>) -> *const Self {
+    Arc::into_raw(Arc::new(Self {
+      object,
+      interface: None,
+    }))
+  }
+
+  /// Borrows the handle behind a pointer passed by C code.
+  ///
+  /// Returns `None` if the pointer is null.
+  ///
+  /// # Safety
+  /// The pointer must be null or a handle, that is not released before the end of `'a`.
+  #[must_use]
+  pub const unsafe fn from_raw<'a>(handle: *const Self) -> Option<&'a Self> {
+    // SAFETY: Guaranteed by the caller.
+    unsafe { handle.as_ref() }
+  }
+
+  /// Returns the object of the handle.
+  #[must_use]
+  pub fn object(&self) -> &(dyn TraitcastableAny + Send + Sync) {
+    &*self.object
+  }
+
+  /// Returns the `StableId` of the interface of the handle, or `None` for handles to the object itself.
+  #[must_use]
+  pub const fn interface(&self) -> Option<StableId> {
+    self.interface
+  }
+
+  /// Returns the object as `Target`, or `None` if the handle is not for the interface of `Target`.
+  ///
+  /// # Safety
+  /// See `TraitcastTarget::metadata_stable`.
+  #[must_use]
+  pub unsafe fn downcast_interface<Target: StableTypeId + ?Sized>(&self) -> Option<&Target> {
+    if self.interface != Some(Target::STABLE_ID) {
+      return None;
+    }
+    let object: &dyn TraitcastableAny = self.object();
+    // SAFETY: Guaranteed by the caller.
+    unsafe { object.downcast_ref_stable() }
+  }
+
+  /// Returns whether the object can be cast to the interface.
+  fn supports(&self, interface: StableId) -> bool {
+    let object: &dyn TraitcastableAny = self.object();
+    object.cast_stable(interface).is_some()
+  }
+}
+
+/// Increments the reference count of the handle and returns it.
+///
+/// Returns null if the handle is null.
+///
+/// # Safety
+/// The handle must be null or a handle, that was not released yet.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn trait_cast_handle_retain(
+  handle: *const TraitcastHandle,
+) -> *const TraitcastHandle {
+  if !handle.is_null() {
+    // SAFETY: The caller guarantees that the handle was created by `Arc::into_raw` and is still alive.
+    unsafe { Arc::increment_strong_count(handle) };
+  }
+  handle
+}
+
+/// Decrements the reference count of the handle and drops it once the count reaches zero.
+///
+/// The object is dropped once all handles to it are dropped. Does nothing if the handle is null.
+///
+/// # Safety
+/// The handle must be null or a handle, that was not released yet.
+/// It must not be used afterwards, unless it was retained before.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn trait_cast_handle_release(handle: *const TraitcastHandle) {
+  if !handle.is_null() {
+    // SAFETY: The caller guarantees that the handle was created by `Arc::into_raw` and is still alive.
+    unsafe { Arc::decrement_strong_count(handle) };
+  }
+}
+
+/// Returns whether the object of the handle supports the interface with the raw `StableId`.
+///
+/// Returns false if the handle is null.
+///
+/// # Safety
+/// The handle must be null or a handle, that was not released yet.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn trait_cast_handle_supports(
+  handle: *const TraitcastHandle,
+  interface_id: u64,
+) -> bool {
+  // SAFETY: Guaranteed by the caller.
+  unsafe { TraitcastHandle::from_raw(handle) }
+    .is_some_and(|handle| handle.supports(StableId::from_raw(interface_id)))
+}
+
+/// Returns a new handle for the interface with the raw `StableId` of the object of the handle.
+///
+/// The new handle has a reference count of one and must be released separately.
+/// Returns null if the object doesn't support the interface or the handle is null.
+///
+/// # Safety
+/// The handle must be null or a handle, that was not released yet.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn trait_cast_handle_query_interface(
+  handle: *const TraitcastHandle,
+  interface_id: u64,
+) -> *const TraitcastHandle {
+  let interface = StableId::from_raw(interface_id);
+  // SAFETY: Guaranteed by the caller.
+  match unsafe { TraitcastHandle::from_raw(handle) } {
+    Some(handle) if handle.supports(interface) => Arc::into_raw(Arc::new(TraitcastHandle {
+      object: Arc::clone(&handle.object),
+      interface: Some(interface),
+    })),
+    _ => ptr::null(),
+  }
+}
+
+/// Returns the raw `StableId` of the interface of the handle.
+///
+/// Returns zero for handles to the object itself and null handles.
+///
+/// # Safety
+/// The handle must be null or a handle, that was not released yet.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn trait_cast_handle_interface_id(handle: *const TraitcastHandle) -> u64 {
+  // SAFETY: Guaranteed by the caller.
+  unsafe { TraitcastHandle::from_raw(handle) }
+    .and_then(TraitcastHandle::interface)
+    .map_or(0, StableId::to_raw)
+}
+
+/// Returns whether both handles refer to the same object.
+///
+/// Returns false if any of the handles is null.
+///
+/// # Safety
+/// The handles must be null or handles, that were not released yet.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn trait_cast_handle_same_object(
+  a: *const TraitcastHandle,
+  b: *const TraitcastHandle,
+) -> bool {
+  // SAFETY: Guaranteed by the caller.
+  match unsafe { (TraitcastHandle::from_raw(a), TraitcastHandle::from_raw(b)) } {
+    (Some(a), Some(b)) => Arc::ptr_eq(&a.object, &b.object),
+    _ => false,
+  }
+}
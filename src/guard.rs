@@ -0,0 +1,57 @@
+use core::cell::{Ref, RefMut};
+
+#[cfg(feature = "std")]
+use std::sync::{
+  MappedMutexGuard, MappedRwLockReadGuard, MappedRwLockWriteGuard, MutexGuard, RwLockReadGuard,
+  RwLockWriteGuard,
+};
+
+use crate::TraitcastableAnyInfra;
+
+/// Extension Trait to implement over borrow and lock guards (`Ref`, `RefMut`, `MutexGuard`, ...).
+///
+/// Narrows a guard over a `dyn TraitcastableAny` to a guard over `Target` while the borrow or lock is still held.
+pub trait TraitcastableGuardExt<Target: ?Sized + 'static>: Sized {
+  /// The type that will be returned on a successful cast. Something like `RefMut<'b, Target>`.
+  type Output;
+
+  /// Same as `downcast_ref` and `downcast_mut`, except that it maps the guard in place.
+  ///
+  /// # Errors
+  /// In case a cast is impossible the original guard is returned as the error type.
+  /// Otherwise the borrow or lock would have to be released and reacquired.
+  fn cast_guard(self) -> Result<Self::Output, Self>;
+}
+
+macro_rules! implement_guards {
+  ($(#[$attr:meta])*) => {};
+  ($(#[$attr:meta])* $guard:ident => $output:ident, $map:ident, $downcast:ident; $($rest:tt)*) => {
+    $(#[$attr])*
+    impl<'b, Src: TraitcastableAnyInfra<Target> + ?Sized, Target: ?Sized + 'static>
+      TraitcastableGuardExt<Target> for $guard<'b, Src>
+    {
+      type Output = $output<'b, Target>;
+
+      fn cast_guard(self) -> Result<Self::Output, Self> {
+        $guard::$map(self, |src| src.$downcast())
+      }
+    }
+    implement_guards! { $(#[$attr])* $($rest)* }
+  };
+}
+
+implement_guards! {
+  Ref => Ref, filter_map, downcast_ref;
+  RefMut => RefMut, filter_map, downcast_mut;
+}
+
+implement_guards! {
+  #[cfg(feature = "std")]
+  #[doc(cfg(feature = "std"))]
+  MutexGuard => MappedMutexGuard, try_map, downcast_mut;
+  MappedMutexGuard => MappedMutexGuard, try_map, downcast_mut;
+  RwLockReadGuard => MappedRwLockReadGuard, try_map, downcast_ref;
+  MappedRwLockReadGuard => MappedRwLockReadGuard, try_map, downcast_ref;
+  RwLockWriteGuard => MappedRwLockWriteGuard, try_map, downcast_mut;
+  MappedRwLockWriteGuard => MappedRwLockWriteGuard, try_map, downcast_mut;
+}
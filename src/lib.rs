@@ -11,19 +11,98 @@
   trait_upcasting,    // Needed to avoid reimplementing Any
   min_specialization, // Needed to unify the interface between downcast and traitcast (could be avoided with !Trait bounds or trait generics)
   ptr_metadata,       // Needed to deal with pointer address(and provenance) separately from metadata
-  doc_cfg             // For nicer Docs
+  doc_cfg,            // For nicer Docs
+  unsize              // Needed to construct a `StackBox` of an unsized type
 )]
 #![cfg_attr(feature = "downcast_unchecked", feature(downcast_unchecked))]
+#![cfg_attr(feature = "std", feature(mapped_lock_guards))]
 
 #[cfg(feature = "alloc")]
 extern crate alloc;
+#[cfg(feature = "std")]
+extern crate std;
 
 mod trait_cast;
 pub use trait_cast::*;
 
+mod guard;
+pub use guard::*;
+
+mod erased;
+pub use erased::*;
+
+mod scoped;
+
+mod provide;
+pub use provide::*;
+
+mod cast_error;
+pub use cast_error::*;
+
+mod stable_id;
+pub use stable_id::*;
+
+mod dyn_cmp;
+pub use dyn_cmp::*;
+
+mod stack_box;
+pub use stack_box::*;
+
+#[cfg(feature = "alloc")]
+mod adapter;
+#[cfg(feature = "alloc")]
+pub use adapter::*;
+
+#[cfg(feature = "alloc")]
+mod clone;
+#[cfg(feature = "alloc")]
+pub use clone::*;
+
+#[cfg(feature = "alloc")]
+mod composite;
+#[cfg(feature = "alloc")]
+pub use composite::*;
+
+#[cfg(feature = "alloc")]
+mod dispatch;
+#[cfg(feature = "alloc")]
+pub use dispatch::*;
+
+#[cfg(feature = "alloc")]
+mod event_bus;
+#[cfg(feature = "alloc")]
+pub use event_bus::*;
+
+#[cfg(feature = "alloc")]
+mod registry;
+#[cfg(feature = "alloc")]
+pub use registry::*;
+
+#[cfg(feature = "alloc")]
+mod service;
+#[cfg(feature = "alloc")]
+pub use service::*;
+
+#[cfg(feature = "alloc")]
+mod trait_map;
+#[cfg(feature = "alloc")]
+pub use trait_map::*;
+
+#[cfg(feature = "alloc")]
+mod unique;
+#[cfg(feature = "alloc")]
+pub use unique::*;
+
+#[cfg(feature = "std_impls")]
+mod std_impls;
+
+#[cfg(feature = "ffi")]
+#[doc(cfg(feature = "ffi"))]
+pub mod ffi;
+
 mod decl_macro;
 
-pub use trait_cast_macros::make_trait_castable;
+pub use trait_cast_macros::{make_trait_castable, traitcast};
 
 #[cfg(test)]
 mod test;
@@ -0,0 +1,92 @@
+use core::{
+  any::{TypeId, type_name},
+  fmt::{self, Debug, Formatter},
+  marker::PhantomData,
+  ptr,
+};
+
+use crate::TraitcastableAny;
+
+/// A request for a reference to a value of a single type, which is answered by `TraitcastableAny::provide`.
+///
+/// Modeled on `core::error::Request`. The first value supplied for the requested type is kept.
+pub struct Request<'a> {
+  target: TypeId,
+  target_name: &'static str,
+  /// Points to an `Option<&'a Target>`, where `Target` is the type with the `TypeId`.
+  slot: *mut (),
+  /// Makes `'a` invariant, so shorter lived references can't be supplied.
+  _marker: PhantomData<fn(&'a ()) -> &'a ()>,
+}
+
+impl Debug for Request<'_> {
+  fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+    f.debug_struct("Request")
+      .field("target", &self.target_name)
+      .finish_non_exhaustive()
+  }
+}
+
+impl<'a> Request<'a> {
+  /// Returns the slot for the value if `Target` is requested and no value was supplied yet.
+  fn slot<Target: ?Sized + 'static>(&mut self) -> Option<&mut Option<&'a Target>> {
+    if self.target != TypeId::of::<Target>() {
+      return None;
+    }
+    // SAFETY:
+    // The slot points to an `Option<&'a Target>` for the type with the `TypeId`, which outlives the request.
+    let slot = unsafe { &mut *self.slot.cast::<Option<&'a Target>>() };
+    slot.is_none().then_some(slot)
+  }
+
+  /// Supplies the value if `Target` is requested.
+  pub fn provide_ref<Target: ?Sized + 'static>(&mut self, value: &'a Target) -> &mut Self {
+    if let Some(slot) = self.slot() {
+      *slot = Some(value);
+    }
+    self
+  }
+
+  /// Supplies the value returned by the closure if `Target` is requested.
+  ///
+  /// The closure is only called if it is, so creating the value lazily is cheap for other requests.
+  pub fn provide_ref_with<Target: ?Sized + 'static>(
+    &mut self,
+    fulfil: impl FnOnce() -> &'a Target,
+  ) -> &mut Self {
+    if let Some(slot) = self.slot() {
+      *slot = Some(fulfil());
+    }
+    self
+  }
+
+  /// Returns true if `Target` is requested and no value was supplied yet.
+  #[must_use]
+  pub fn would_be_satisfied_by_ref_of<Target: ?Sized + 'static>(&self) -> bool {
+    if self.target != TypeId::of::<Target>() {
+      return false;
+    }
+    // SAFETY:
+    // The slot points to an `Option<&'a Target>` for the type with the `TypeId`, which outlives the request.
+    unsafe { (*self.slot.cast::<Option<&'a Target>>()).is_none() }
+  }
+}
+
+/// Requests a reference to `Target` from the `TraitcastableAny::provide` implementation of the object.
+///
+/// Unlike `downcast_ref` this ignores the `traitcast_targets` of the object.
+#[must_use]
+pub fn request_ref<'a, Target: ?Sized + 'static>(
+  object: &'a dyn TraitcastableAny,
+) -> Option<&'a Target> {
+  let mut value: Option<&'a Target> = None;
+  // The lifetime of the request must be the one of the slot.
+  let mut request: Request<'a> = Request {
+    target: TypeId::of::<Target>(),
+    target_name: type_name::<Target>(),
+    slot: ptr::from_mut(&mut value).cast::<()>(),
+    _marker: PhantomData,
+  };
+  object.provide(&mut request);
+  value
+}
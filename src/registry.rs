@@ -0,0 +1,151 @@
+use core::{
+  any::{Any, TypeId},
+  ptr::{self, DynMetadata, Pointee},
+};
+
+use alloc::{boxed::Box, collections::BTreeMap};
+
+use crate::TraitcastableAny;
+
+mod sealed {
+  pub trait Sealed {}
+}
+
+/// `dyn Any` or one of its `Send`/`Sync` variants, which an `AnyRegistry` can convert.
+///
+/// This trait is sealed and implemented for `dyn Any`, `dyn Any + Send` and `dyn Any + Send + Sync`.
+pub trait RegistryAny: sealed::Sealed {
+  /// The `dyn TraitcastableAny` with the same markers.
+  type Castable: ?Sized + Pointee<Metadata = DynMetadata<Self::Castable>>;
+
+  /// Returns the `TypeId` of the concrete type.
+  fn concrete_type_id(&self) -> TypeId;
+
+  /// Converts the metadata of the concrete type to the one of `Castable`.
+  ///
+  /// # Safety
+  /// The concrete type the metadata was created for must implement the markers of `Castable`.
+  unsafe fn castable_metadata(
+    metadata: DynMetadata<dyn TraitcastableAny>,
+  ) -> DynMetadata<Self::Castable>;
+}
+
+macro_rules! implement_registry_any_with_markers {
+  ($($(+)? $traits:ident)*) => {
+    impl sealed::Sealed for dyn Any $(+ $traits)* {}
+
+    impl RegistryAny for dyn Any $(+ $traits)* {
+      type Castable = dyn TraitcastableAny $(+ $traits)*;
+
+      fn concrete_type_id(&self) -> TypeId {
+        let any: &dyn Any = self;
+        any.type_id()
+      }
+
+      unsafe fn castable_metadata(
+        metadata: DynMetadata<dyn TraitcastableAny>,
+      ) -> DynMetadata<Self::Castable> {
+        // SAFETY:
+        // Markers don't change the vtable, so the metadata only differs in its type.
+        // The caller guarantees that the concrete type implements the markers.
+        unsafe {
+          ptr::from_ref(&metadata)
+            .cast::<DynMetadata<Self::Castable>>()
+            .read()
+        }
+      }
+    }
+  };
+}
+
+implement_registry_any_with_markers!();
+implement_registry_any_with_markers!(Send);
+implement_registry_any_with_markers!(Send + Sync);
+
+/// A registry of concrete types, which allows converting values erased as `dyn Any` back into `dyn TraitcastableAny`.
+///
+/// `Any` has no vtable for `TraitcastableAny`, so the registry stores the `DynMetadata<dyn TraitcastableAny>`
+///  of every registered concrete type keyed by its `TypeId`.
+///
+/// `dyn Any + Send` and `dyn Any + Send + Sync` are converted into the `dyn TraitcastableAny` with the same markers.
+#[derive(Debug, Default)]
+pub struct AnyRegistry {
+  metadata: BTreeMap<TypeId, DynMetadata<dyn TraitcastableAny>>,
+}
+
+impl AnyRegistry {
+  /// Creates an empty registry.
+  #[must_use]
+  pub const fn new() -> Self {
+    Self {
+      metadata: BTreeMap::new(),
+    }
+  }
+
+  /// Registers the concrete type `T`, so that values of it can be converted from `dyn Any`.
+  pub fn register<T: TraitcastableAny>(&mut self) -> &mut Self {
+    let dyn_ptr: *const dyn TraitcastableAny = ptr::null::<T>();
+    self
+      .metadata
+      .insert(TypeId::of::<T>(), ptr::metadata(dyn_ptr));
+    self
+  }
+
+  /// Returns true if the concrete type with the `TypeId` was registered.
+  #[must_use]
+  pub fn contains(&self, type_id: TypeId) -> bool {
+    self.metadata.contains_key(&type_id)
+  }
+
+  /// Returns the metadata of the concrete type of `any` as the `dyn TraitcastableAny` with the same markers.
+  fn metadata_of<A: ?Sized + RegistryAny>(&self, any: &A) -> Option<DynMetadata<A::Castable>> {
+    let metadata = *self.metadata.get(&any.concrete_type_id())?;
+    // SAFETY:
+    // The metadata was registered for the concrete type of `any`, which implements the markers of `A`.
+    Some(unsafe { A::castable_metadata(metadata) })
+  }
+
+  /// Converts a `&dyn Any` into a `&dyn TraitcastableAny`.
+  ///
+  /// Returns `None` if the concrete type of `any` was not registered.
+  #[must_use]
+  pub fn from_any_ref<'a, A: ?Sized + RegistryAny>(&self, any: &'a A) -> Option<&'a A::Castable> {
+    let metadata = self.metadata_of(any)?;
+    let raw_ptr = ptr::from_ref::<A>(any).cast::<()>();
+    // SAFETY:
+    // The metadata was registered for the same concrete type as the one behind `any`.
+    Some(unsafe { &*ptr::from_raw_parts::<A::Castable>(raw_ptr, metadata) })
+  }
+
+  /// Converts a `&mut dyn Any` into a `&mut dyn TraitcastableAny`.
+  ///
+  /// Returns `None` if the concrete type of `any` was not registered.
+  #[must_use]
+  pub fn from_any_mut<'a, A: ?Sized + RegistryAny>(
+    &self,
+    any: &'a mut A,
+  ) -> Option<&'a mut A::Castable> {
+    let metadata = self.metadata_of(any)?;
+    let raw_ptr = ptr::from_mut::<A>(any).cast::<()>();
+    // SAFETY:
+    // The metadata was registered for the same concrete type as the one behind `any`.
+    Some(unsafe { &mut *ptr::from_raw_parts_mut::<A::Castable>(raw_ptr, metadata) })
+  }
+
+  /// Converts a `Box<dyn Any>` into a `Box<dyn TraitcastableAny>`.
+  ///
+  /// # Errors
+  /// In case the concrete type of `any` was not registered the original box is returned as the error type.
+  pub fn from_any_box<A: ?Sized + RegistryAny>(
+    &self,
+    any: Box<A>,
+  ) -> Result<Box<A::Castable>, Box<A>> {
+    let Some(metadata) = self.metadata_of(any.as_ref()) else {
+      return Err(any);
+    };
+    let raw_ptr = Box::into_raw(any).cast::<()>();
+    // SAFETY:
+    // The pointer originates from a `Box` and the metadata was registered for the same concrete type.
+    Ok(unsafe { Box::from_raw(ptr::from_raw_parts_mut::<A::Castable>(raw_ptr, metadata)) })
+  }
+}
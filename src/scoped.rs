@@ -0,0 +1,72 @@
+use core::{any::TypeId, ptr};
+
+use crate::{TraitcastTarget, TraitcastableAny};
+
+/// Finds the scoped target for `Target`, which is guarded by `Key`.
+fn find_scoped_target<Target: ?Sized + 'static, Key: ?Sized + 'static>(
+  targets: &[TraitcastTarget],
+) -> Option<&TraitcastTarget> {
+  targets.iter().find(|target| {
+    target.target_type_id() == TypeId::of::<Target>()
+      && target.key_type_id() == Some(TypeId::of::<Key>())
+  })
+}
+
+macro_rules! implement_scoped_with_markers {
+  ($($(+)? $traits:ident)*) => {
+    impl dyn TraitcastableAny $(+ $traits)* {
+      /// Returns true if `Target` is a scoped target guarded by `Key`.
+      ///
+      /// Scoped targets are listed as `Target in Key` in the `make_trait_castable` attribute macro.
+      /// Make `Key` constructible only by the code that should be able to cast, for example with a private field.
+      #[must_use]
+      pub fn can_be_scoped<Target: ?Sized + 'static, Key: ?Sized + 'static>(&self, key: &Key) -> bool {
+        let _ = key;
+        find_scoped_target::<Target, Key>(self.traitcast_scoped_targets()).is_some()
+          && self.traitcast_allowed(TypeId::of::<Target>())
+      }
+
+      /// Returns some reference to the inner value if `Target` is a scoped target guarded by `Key`, or `None` if it isn’t.
+      ///
+      /// The reference to the key proves that the caller is allowed to cast to `Target`.
+      /// Scoped targets are never found by `downcast_ref` or the other casts without a key.
+      #[must_use]
+      pub fn downcast_ref_scoped<Target: ?Sized + 'static, Key: ?Sized + 'static>(
+        &self,
+        key: &Key,
+      ) -> Option<&Target> {
+        let _ = key;
+        if !self.traitcast_allowed(TypeId::of::<Target>()) {
+          return None;
+        }
+        let metadata = find_scoped_target::<Target, Key>(self.traitcast_scoped_targets())?.metadata::<Target>()?;
+        let raw_ptr = ptr::from_ref(self).cast::<()>();
+        // SAFETY:
+        // The metadata belongs to a scoped target of the concrete type, which always resolves to the value itself.
+        Some(unsafe { &*ptr::from_raw_parts::<Target>(raw_ptr, metadata) })
+      }
+
+      /// Mutable variant of `downcast_ref_scoped`.
+      #[must_use]
+      pub fn downcast_mut_scoped<Target: ?Sized + 'static, Key: ?Sized + 'static>(
+        &mut self,
+        key: &Key,
+      ) -> Option<&mut Target> {
+        let _ = key;
+        if !TraitcastableAny::traitcast_allowed(&*self, TypeId::of::<Target>()) {
+          return None;
+        }
+        let metadata =
+          find_scoped_target::<Target, Key>(TraitcastableAny::traitcast_scoped_targets(&*self))?.metadata::<Target>()?;
+        let raw_ptr = ptr::from_mut(self).cast::<()>();
+        // SAFETY:
+        // The metadata belongs to a scoped target of the concrete type, which always resolves to the value itself.
+        Some(unsafe { &mut *ptr::from_raw_parts_mut::<Target>(raw_ptr, metadata) })
+      }
+    }
+  };
+}
+
+implement_scoped_with_markers!();
+implement_scoped_with_markers!(Send);
+implement_scoped_with_markers!(Send + Sync);
@@ -0,0 +1,330 @@
+use core::{
+  any::{TypeId, type_name},
+  cell::{Cell, OnceCell},
+  error::Error,
+  fmt::{self, Debug, Display, Formatter},
+};
+
+use alloc::{
+  boxed::Box,
+  string::{String, ToString},
+  sync::Arc,
+  vec::Vec,
+};
+
+use crate::{TraitcastableAny, TraitcastableAnyInfra, TraitcastableAnyInfraExt};
+
+/// A service of a `ServiceContainer`.
+pub type Service = Arc<dyn TraitcastableAny + Send + Sync>;
+
+/// Creates a lazy service of a `ServiceContainer`. May resolve the services it depends on from the container.
+pub type ServiceFactory = Box<dyn Fn(&ServiceContainer) -> Result<Service, ServiceError>>;
+
+/// Error returned when resolving a service from a `ServiceContainer` fails.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ServiceError {
+  /// No service can be resolved as the type.
+  NotFound {
+    /// The `type_name` of the requested type.
+    type_name: &'static str,
+    /// The requested name, if any.
+    name: Option<String>,
+  },
+  /// More than one service can be resolved as the type.
+  Ambiguous {
+    /// The `type_name` of the requested type.
+    type_name: &'static str,
+    /// The number of services, that can be resolved as the type.
+    count: usize,
+  },
+  /// The factory of the service resolved the service itself, directly or through other factories.
+  Cycle {
+    /// The `type_name` of the requested type.
+    type_name: &'static str,
+  },
+  /// A factory created a service, that can't be cast to the type it was registered as.
+  NotCastable {
+    /// The `type_name` of the type the factory was registered as.
+    type_name: &'static str,
+  },
+  /// A service with the name was already registered.
+  DuplicateName {
+    /// The name of the service.
+    name: String,
+  },
+}
+
+impl Display for ServiceError {
+  fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+    match self {
+      Self::NotFound {
+        type_name,
+        name: Some(name),
+      } => write!(
+        f,
+        "no service named `{name}` can be resolved as `{type_name}`"
+      ),
+      Self::NotFound {
+        type_name,
+        name: None,
+      } => write!(f, "no service can be resolved as `{type_name}`"),
+      Self::Ambiguous { type_name, count } => {
+        write!(f, "{count} services can be resolved as `{type_name}`")
+      },
+      Self::Cycle { type_name } => write!(f, "cyclic dependency while resolving `{type_name}`"),
+      Self::NotCastable { type_name } => write!(
+        f,
+        "the factory registered as `{type_name}` created a service, that can't be cast to it"
+      ),
+      Self::DuplicateName { name } => write!(f, "a service named `{name}` was already registered"),
+    }
+  }
+}
+
+impl Error for ServiceError {}
+
+struct Registration {
+  name: Option<String>,
+  /// The `TypeId` and `type_name` of the type a lazy service was registered as.
+  /// It can only be resolved as other types once it was created.
+  registered_as: Option<(TypeId, &'static str)>,
+  factory: Option<ServiceFactory>,
+  instance: OnceCell<Service>,
+  /// Set while the factory runs to detect cycles.
+  creating: Cell<bool>,
+}
+
+impl Registration {
+  /// Returns true if the service can be resolved as `Target`.
+  ///
+  /// Resolving casts the `Arc` of the service, so only targets resolving to the service itself count,
+  ///  not the ones reached through a delegate, a field or `provide`.
+  fn can_be<Target: ?Sized + Send + Sync + 'static>(&self) -> bool {
+    self.instance.get().map_or_else(
+      || matches!(self.registered_as, Some((type_id, _)) if type_id == TypeId::of::<Target>()),
+      |instance| {
+        TraitcastableAnyInfra::<Target>::downcast_ref_in_place(instance.as_ref()).is_some()
+      },
+    )
+  }
+
+  /// Returns the instance of the service and creates it if necessary.
+  ///
+  /// # Errors
+  /// In case the factory fails or is already running.
+  fn instance(&self, container: &ServiceContainer) -> Result<&Service, ServiceError> {
+    if let Some(instance) = self.instance.get() {
+      return Ok(instance);
+    }
+    let (factory, (_, type_name)) = self
+      .factory
+      .as_ref()
+      .zip(self.registered_as)
+      .expect("services without an instance have a factory");
+    if self.creating.replace(true) {
+      return Err(ServiceError::Cycle { type_name });
+    }
+    let instance = factory(container);
+    self.creating.set(false);
+    let instance = instance?;
+    Ok(self.instance.get_or_init(|| instance))
+  }
+}
+
+/// A dependency injection container, which resolves services by the traits they can be cast to.
+///
+/// Services are registered as `Arc<dyn TraitcastableAny + Send + Sync>` and resolved as `Arc<dyn Trait>`.
+/// A service can be resolved as every cast target of it, all of them share the same object.
+/// Targets reached through a delegate, a field or `provide` are not shared, so services can't be resolved as them.
+/// Lazy services are created by their factory on the first resolution. Until then they can only be resolved
+///  as the type they were registered as.
+///
+/// The container itself is meant to be used while bootstrapping on a single thread,
+///  the resolved services can be sent to other threads.
+#[derive(Default)]
+pub struct ServiceContainer {
+  registrations: Vec<Registration>,
+}
+
+impl Debug for ServiceContainer {
+  fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+    f.debug_struct("ServiceContainer")
+      .field("services", &self.registrations.len())
+      .finish_non_exhaustive()
+  }
+}
+
+impl ServiceContainer {
+  /// Creates an empty container.
+  #[must_use]
+  pub const fn new() -> Self {
+    Self {
+      registrations: Vec::new(),
+    }
+  }
+
+  /// Adds a registration.
+  ///
+  /// # Errors
+  /// In case a service with the name was already registered.
+  fn push(
+    &mut self,
+    name: Option<String>,
+    registered_as: Option<(TypeId, &'static str)>,
+    factory: Option<ServiceFactory>,
+    instance: OnceCell<Service>,
+  ) -> Result<&mut Self, ServiceError> {
+    if let Some(name) = &name {
+      if self
+        .registrations
+        .iter()
+        .any(|registration| registration.name.as_ref() == Some(name))
+      {
+        return Err(ServiceError::DuplicateName { name: name.clone() });
+      }
+    }
+    self.registrations.push(Registration {
+      name,
+      registered_as,
+      factory,
+      instance,
+      creating: Cell::new(false),
+    });
+    Ok(self)
+  }
+
+  /// Registers a service.
+  pub fn register(&mut self, service: Service) -> &mut Self {
+    self
+      .push(None, None, None, OnceCell::from(service))
+      .expect("unnamed services can't collide")
+  }
+
+  /// Registers a service, that can also be resolved by its name.
+  ///
+  /// # Errors
+  /// In case a service with the name was already registered.
+  pub fn register_named(
+    &mut self,
+    name: impl Into<String>,
+    service: Service,
+  ) -> Result<&mut Self, ServiceError> {
+    self.push(Some(name.into()), None, None, OnceCell::from(service))
+  }
+
+  /// Registers a lazy service as `Target`, which is created by the factory on its first resolution.
+  pub fn register_factory<Target: ?Sized + Send + Sync + 'static>(
+    &mut self,
+    factory: impl Fn(&Self) -> Result<Service, ServiceError> + 'static,
+  ) -> &mut Self {
+    self
+      .push(
+        None,
+        Some((TypeId::of::<Target>(), type_name::<Target>())),
+        Some(Box::new(factory)),
+        OnceCell::new(),
+      )
+      .expect("unnamed services can't collide")
+  }
+
+  /// Registers a lazy service as `Target`, that can also be resolved by its name.
+  ///
+  /// # Errors
+  /// In case a service with the name was already registered.
+  pub fn register_named_factory<Target: ?Sized + Send + Sync + 'static>(
+    &mut self,
+    name: impl Into<String>,
+    factory: impl Fn(&Self) -> Result<Service, ServiceError> + 'static,
+  ) -> Result<&mut Self, ServiceError> {
+    self.push(
+      Some(name.into()),
+      Some((TypeId::of::<Target>(), type_name::<Target>())),
+      Some(Box::new(factory)),
+      OnceCell::new(),
+    )
+  }
+
+  /// Returns the instance of the service as `Target` and creates it if necessary.
+  ///
+  /// # Errors
+  /// In case creating it fails or it can't be cast to `Target`.
+  fn resolve_registration<Target: ?Sized + Send + Sync + 'static>(
+    &self,
+    registration: &Registration,
+  ) -> Result<Arc<Target>, ServiceError> {
+    let instance = registration.instance(self)?;
+    Arc::clone(instance)
+      .downcast()
+      .map_err(|_| ServiceError::NotCastable {
+        type_name: type_name::<Target>(),
+      })
+  }
+
+  /// Resolves the only service, that can be cast to `Target`.
+  ///
+  /// # Errors
+  /// In case there is not exactly one such service or creating a lazy service fails.
+  pub fn resolve<Target: ?Sized + Send + Sync + 'static>(
+    &self,
+  ) -> Result<Arc<Target>, ServiceError> {
+    let mut candidates = self
+      .registrations
+      .iter()
+      .filter(|registration| registration.can_be::<Target>());
+    let (Some(registration), None) = (candidates.next(), candidates.next()) else {
+      let count = self
+        .registrations
+        .iter()
+        .filter(|registration| registration.can_be::<Target>())
+        .count();
+      return Err(if count == 0 {
+        ServiceError::NotFound {
+          type_name: type_name::<Target>(),
+          name: None,
+        }
+      } else {
+        ServiceError::Ambiguous {
+          type_name: type_name::<Target>(),
+          count,
+        }
+      });
+    };
+    self.resolve_registration(registration)
+  }
+
+  /// Resolves the service with the name as `Target`.
+  ///
+  /// # Errors
+  /// In case there is no such service, it can't be cast to `Target` or creating it fails.
+  pub fn resolve_named<Target: ?Sized + Send + Sync + 'static>(
+    &self,
+    name: &str,
+  ) -> Result<Arc<Target>, ServiceError> {
+    let not_found = || ServiceError::NotFound {
+      type_name: type_name::<Target>(),
+      name: Some(name.to_string()),
+    };
+    let registration = self
+      .registrations
+      .iter()
+      .find(|registration| registration.name.as_deref() == Some(name))
+      .filter(|registration| registration.can_be::<Target>())
+      .ok_or_else(not_found)?;
+    self.resolve_registration(registration)
+  }
+
+  /// Resolves all services, that can be cast to `Target`, in the order of their registration.
+  ///
+  /// # Errors
+  /// In case creating a lazy service fails.
+  pub fn resolve_all<Target: ?Sized + Send + Sync + 'static>(
+    &self,
+  ) -> Result<Vec<Arc<Target>>, ServiceError> {
+    self
+      .registrations
+      .iter()
+      .filter(|registration| registration.can_be::<Target>())
+      .map(|registration| self.resolve_registration(registration))
+      .collect()
+  }
+}
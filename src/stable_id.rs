@@ -0,0 +1,319 @@
+use core::{
+  error::Error,
+  fmt::{self, Display, Formatter},
+};
+
+#[cfg(feature = "alloc")]
+use core::any::{TypeId, type_name};
+
+#[cfg(feature = "alloc")]
+use alloc::collections::BTreeMap;
+
+#[cfg(feature = "alloc")]
+use crate::{ErasedTraitRef, TraitcastTarget, TraitcastableAny};
+
+const FNV_OFFSET_BASIS: u64 = 0xcbf2_9ce4_8422_2325;
+const FNV_PRIME: u64 = 0x0000_0100_0000_01b3;
+
+/// An identifier of a type, that is stable across compilations (unlike `TypeId`).
+///
+/// Either chosen explicitly or derived from a declared name like `com.acme.Renderer.v1`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct StableId(u64);
+
+impl StableId {
+  /// Creates a `StableId` from an explicitly chosen number.
+  #[must_use]
+  pub const fn from_raw(id: u64) -> Self {
+    Self(id)
+  }
+
+  /// Creates a `StableId` by hashing a declared name with the 64 bit FNV-1a hash.
+  #[must_use]
+  pub const fn from_name(name: &str) -> Self {
+    let bytes = name.as_bytes();
+    let mut hash = FNV_OFFSET_BASIS;
+    let mut i = 0;
+    while i < bytes.len() {
+      hash ^= bytes[i] as u64;
+      hash = hash.wrapping_mul(FNV_PRIME);
+      i += 1;
+    }
+    Self(hash)
+  }
+
+  /// Creates the `StableId` of an interface shared between separately compiled artifacts (like a plugin and its host).
+  ///
+  /// Hashes the name (including a version) of the interface, the ids of the traits it contains,
+  ///  the version of this crate (which determines the layout of the `TraitcastTarget`s)
+  ///  and the version of the compiler (which determines the layout of vtables and trait objects).
+  /// The compiler version is passed in, for example the output of `rustc --version` recorded by a build script.
+  /// Both sides should compare their interface ids before exchanging trait castable objects.
+  #[must_use]
+  pub const fn for_interface(name: &str, compiler: &str, targets: &[Self]) -> Self {
+    let mut hash = Self::from_name(concat!("trait-cast ", env!("CARGO_PKG_VERSION")))
+      .combine(Self::from_name(compiler))
+      .combine(Self::from_name(name));
+    let mut i = 0;
+    while i < targets.len() {
+      hash = hash.combine(targets[i]);
+      i += 1;
+    }
+    hash
+  }
+
+  /// Combines two ids into a new one, order matters.
+  #[must_use]
+  pub const fn combine(self, other: Self) -> Self {
+    let bytes = other.0.to_le_bytes();
+    let mut hash = self.0;
+    let mut i = 0;
+    while i < bytes.len() {
+      hash ^= bytes[i] as u64;
+      hash = hash.wrapping_mul(FNV_PRIME);
+      i += 1;
+    }
+    Self(hash)
+  }
+
+  /// Returns the raw number of the `StableId`.
+  #[must_use]
+  pub const fn to_raw(self) -> u64 {
+    self.0
+  }
+}
+
+impl Display for StableId {
+  fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+    write!(f, "{:#018x}", self.0)
+  }
+}
+
+/// Assigns a `StableId` to a type.
+///
+/// For traits this is implemented on the trait object type `dyn Trait`.
+///
+/// This should generally not be manually implemented, but generated by the `traitcast` attribute macro.
+pub trait StableTypeId: 'static {
+  /// The `StableId` of the type.
+  const STABLE_ID: StableId;
+}
+
+/// The error returned when a `StableId` or a type is registered twice.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DuplicateStableIdError {
+  /// The id is already used by another type.
+  IdTaken {
+    /// The `StableId` that was registered twice.
+    id: StableId,
+    /// The `type_name` of the type that was registered first.
+    existing_type_name: &'static str,
+    /// The `type_name` of the type that was rejected.
+    type_name: &'static str,
+  },
+  /// The type is already registered under another id.
+  TypeRegistered {
+    /// The `type_name` of the type that was registered twice.
+    type_name: &'static str,
+    /// The id the type was registered with first.
+    existing_id: StableId,
+    /// The id that was rejected.
+    id: StableId,
+  },
+}
+
+impl Display for DuplicateStableIdError {
+  fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+    match self {
+      Self::IdTaken {
+        id,
+        existing_type_name,
+        type_name,
+      } => write!(
+        f,
+        "the stable id {id} of `{type_name}` is already used by `{existing_type_name}`"
+      ),
+      Self::TypeRegistered {
+        type_name,
+        existing_id,
+        id,
+      } => write!(
+        f,
+        "`{type_name}` can't be registered with the stable id {id}, since it already has the stable id {existing_id}"
+      ),
+    }
+  }
+}
+
+impl Error for DuplicateStableIdError {}
+
+#[cfg(feature = "alloc")]
+#[derive(Debug, Default)]
+struct StableIdMap {
+  by_id: BTreeMap<StableId, (TypeId, &'static str)>,
+  by_type_id: BTreeMap<TypeId, StableId>,
+}
+
+#[cfg(feature = "alloc")]
+impl StableIdMap {
+  const fn new() -> Self {
+    Self {
+      by_id: BTreeMap::new(),
+      by_type_id: BTreeMap::new(),
+    }
+  }
+
+  /// Maps the id to the type and the other way around.
+  ///
+  /// # Errors
+  /// In case the id is already used by another type or the type already has another id.
+  fn insert<T: ?Sized + 'static>(&mut self, id: StableId) -> Result<(), DuplicateStableIdError> {
+    let type_id = TypeId::of::<T>();
+    if let Some(&(existing_type_id, existing_type_name)) = self.by_id.get(&id) {
+      if existing_type_id == type_id {
+        return Ok(());
+      }
+      return Err(DuplicateStableIdError::IdTaken {
+        id,
+        existing_type_name,
+        type_name: type_name::<T>(),
+      });
+    }
+    if let Some(&existing_id) = self.by_type_id.get(&type_id) {
+      return Err(DuplicateStableIdError::TypeRegistered {
+        type_name: type_name::<T>(),
+        existing_id,
+        id,
+      });
+    }
+    self.by_id.insert(id, (type_id, type_name::<T>()));
+    self.by_type_id.insert(type_id, id);
+    Ok(())
+  }
+}
+
+/// A registry mapping `StableId`s of cast targets and source types to their `TypeId`s.
+///
+/// Allows matching trait castable objects by identifiers that are stable across compilations,
+///  for example to persist which traits an object implements.
+#[cfg(feature = "alloc")]
+#[doc(cfg(feature = "alloc"))]
+#[derive(Debug, Default)]
+pub struct StableIdRegistry {
+  targets: StableIdMap,
+  sources: StableIdMap,
+}
+
+#[cfg(feature = "alloc")]
+impl StableIdRegistry {
+  /// Creates an empty registry.
+  #[must_use]
+  pub const fn new() -> Self {
+    Self {
+      targets: StableIdMap::new(),
+      sources: StableIdMap::new(),
+    }
+  }
+
+  /// Registers the cast target `Target` (something like `dyn Trait`) with its `StableTypeId`.
+  ///
+  /// # Errors
+  /// In case the id is already used by another target or the target already has another id.
+  pub fn register_target<Target: ?Sized + StableTypeId>(
+    &mut self,
+  ) -> Result<&mut Self, DuplicateStableIdError> {
+    self.register_target_with_id::<Target>(Target::STABLE_ID)
+  }
+
+  /// Registers the cast target `Target` (something like `dyn Trait`) with the given id.
+  ///
+  /// # Errors
+  /// In case the id is already used by another target or the target already has another id.
+  pub fn register_target_with_id<Target: ?Sized + 'static>(
+    &mut self,
+    id: StableId,
+  ) -> Result<&mut Self, DuplicateStableIdError> {
+    self.targets.insert::<Target>(id)?;
+    Ok(self)
+  }
+
+  /// Registers the concrete source type `Src` with its `StableTypeId`.
+  ///
+  /// # Errors
+  /// In case the id is already used by another source or the source already has another id.
+  pub fn register_source<Src: TraitcastableAny + StableTypeId>(
+    &mut self,
+  ) -> Result<&mut Self, DuplicateStableIdError> {
+    self.register_source_with_id::<Src>(Src::STABLE_ID)
+  }
+
+  /// Registers the concrete source type `Src` with the given id.
+  ///
+  /// # Errors
+  /// In case the id is already used by another source or the source already has another id.
+  pub fn register_source_with_id<Src: TraitcastableAny>(
+    &mut self,
+    id: StableId,
+  ) -> Result<&mut Self, DuplicateStableIdError> {
+    self.sources.insert::<Src>(id)?;
+    Ok(self)
+  }
+
+  /// Returns the `TypeId` of the cast target registered with the id.
+  #[must_use]
+  pub fn target_type_id(&self, id: StableId) -> Option<TypeId> {
+    self.targets.by_id.get(&id).map(|&(type_id, _)| type_id)
+  }
+
+  /// Returns the id the cast target with the `TypeId` was registered with.
+  #[must_use]
+  pub fn target_id(&self, type_id: TypeId) -> Option<StableId> {
+    self.targets.by_type_id.get(&type_id).copied()
+  }
+
+  /// Returns the `TypeId` of the source type registered with the id.
+  #[must_use]
+  pub fn source_type_id(&self, id: StableId) -> Option<TypeId> {
+    self.sources.by_id.get(&id).map(|&(type_id, _)| type_id)
+  }
+
+  /// Returns the id the source type with the `TypeId` was registered with.
+  #[must_use]
+  pub fn source_id(&self, type_id: TypeId) -> Option<StableId> {
+    self.sources.by_type_id.get(&type_id).copied()
+  }
+
+  /// Returns the id of the concrete type of `castable`.
+  #[must_use]
+  pub fn source_id_of(&self, castable: &dyn TraitcastableAny) -> Option<StableId> {
+    self.source_id(TraitcastableAny::type_id(castable))
+  }
+
+  /// Looks up the cast target registered with the id on `castable`.
+  ///
+  /// Returns `None` if no target is registered with the id or `castable` can't be cast to it.
+  #[must_use]
+  pub fn find_target<'a>(
+    &self,
+    castable: &'a dyn TraitcastableAny,
+    id: StableId,
+  ) -> Option<ErasedTraitRef<'a>> {
+    castable.cast_erased(self.target_type_id(id)?)
+  }
+
+  /// Returns the ids of all registered cast targets `castable` can currently be cast to.
+  ///
+  /// Targets whose guard rejects the cast are skipped.
+  /// Targets reached through a delegate or provided values are not listed, since they can't be enumerated.
+  pub fn target_ids_of<'a>(
+    &'a self,
+    castable: &'a dyn TraitcastableAny,
+  ) -> impl Iterator<Item = StableId> + 'a {
+    castable
+      .traitcast_targets()
+      .iter()
+      .map(TraitcastTarget::target_type_id)
+      .filter(|&target| TraitcastableAny::traitcast_allowed(castable, target))
+      .filter_map(|target| self.target_id(target))
+  }
+}
@@ -0,0 +1,126 @@
+use core::{
+  fmt::{self, Debug, Formatter},
+  marker::{PhantomData, Unsize},
+  mem::{ManuallyDrop, MaybeUninit, align_of, size_of},
+  ops::{Deref, DerefMut},
+  ptr::{self, Pointee},
+};
+
+use crate::{TraitcastableAny, TraitcastableAnyInfra, TraitcastableAnyInfraExt};
+
+/// The alignment of the inline storage of a `StackBox`.
+/// Values with a larger alignment can't be stored in a `StackBox`.
+pub const STACK_BOX_ALIGN: usize = 16;
+
+#[repr(C, align(16))]
+struct Storage<const N: usize>(MaybeUninit<[u8; N]>);
+
+/// An owning container that stores its value inline in `N` bytes instead of allocating.
+///
+/// Allows owning heterogeneous values and casting them without the `alloc` feature.
+/// The value must fit into `N` bytes and must not require an alignment larger than `STACK_BOX_ALIGN`.
+pub struct StackBox<T: ?Sized, const N: usize> {
+  storage: Storage<N>,
+  metadata: <T as Pointee>::Metadata,
+  _marker: PhantomData<T>,
+}
+
+/// A `StackBox` holding any `TraitcastableAny` of up to `N` bytes.
+pub type InlineCastable<const N: usize> = StackBox<dyn TraitcastableAny, N>;
+
+impl<T: ?Sized, const N: usize> StackBox<T, N> {
+  /// Moves `value` into the inline storage and unsizes it to `T`.
+  ///
+  /// Fails to compile if `S` doesn't fit into `N` bytes or its alignment is larger than `STACK_BOX_ALIGN`.
+  pub fn new<S: Unsize<T>>(value: S) -> Self {
+    const {
+      assert!(
+        size_of::<S>() <= N,
+        "the value does not fit into the `StackBox`"
+      );
+      assert!(
+        align_of::<S>() <= STACK_BOX_ALIGN,
+        "the value requires a larger alignment than the `StackBox` provides"
+      );
+    };
+    let metadata = ptr::metadata(&value as &T);
+    let mut storage = Storage(MaybeUninit::uninit());
+    // SAFETY:
+    // The storage is large and aligned enough for `S`, which we just checked.
+    unsafe { storage.0.as_mut_ptr().cast::<S>().write(value) };
+    Self {
+      storage,
+      metadata,
+      _marker: PhantomData,
+    }
+  }
+
+  const fn as_ptr(&self) -> *const T {
+    ptr::from_raw_parts(self.storage.0.as_ptr().cast::<()>(), self.metadata)
+  }
+
+  fn as_mut_ptr(&mut self) -> *mut T {
+    ptr::from_raw_parts_mut(self.storage.0.as_mut_ptr().cast::<()>(), self.metadata)
+  }
+}
+
+impl<T: ?Sized, const N: usize> Deref for StackBox<T, N> {
+  type Target = T;
+
+  fn deref(&self) -> &T {
+    // SAFETY:
+    // The storage always contains a valid value matching the metadata.
+    unsafe { &*self.as_ptr() }
+  }
+}
+
+impl<T: ?Sized, const N: usize> DerefMut for StackBox<T, N> {
+  fn deref_mut(&mut self) -> &mut T {
+    // SAFETY:
+    // The storage always contains a valid value matching the metadata.
+    unsafe { &mut *self.as_mut_ptr() }
+  }
+}
+
+impl<T: ?Sized, const N: usize> Drop for StackBox<T, N> {
+  fn drop(&mut self) {
+    // SAFETY:
+    // The storage always contains a valid value, which is dropped exactly once here.
+    unsafe { ptr::drop_in_place(self.as_mut_ptr()) };
+  }
+}
+
+impl<T: ?Sized + Debug, const N: usize> Debug for StackBox<T, N> {
+  fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+    Debug::fmt(&**self, f)
+  }
+}
+
+impl<Src: TraitcastableAnyInfra<Target> + ?Sized, Target: ?Sized + 'static, const N: usize>
+  TraitcastableAnyInfraExt<Target> for StackBox<Src, N>
+{
+  type Output = StackBox<Target, N>;
+
+  fn downcast(mut self) -> Result<Self::Output, Self> {
+    let Some(metadata) = (*self)
+      .downcast_mut_in_place()
+      .map(|to_ref| ptr::metadata(to_ref))
+    else {
+      return Err(self);
+    };
+    let this = ManuallyDrop::new(self);
+    Ok(StackBox {
+      // SAFETY:
+      // The value is moved bitwise into the new `StackBox` and `this` is never dropped.
+      storage: unsafe { ptr::read(&this.storage) },
+      metadata,
+      _marker: PhantomData,
+    })
+  }
+
+  #[cfg(feature = "downcast_unchecked")]
+  unsafe fn downcast_unchecked(self) -> Self::Output {
+    // SAFETY: The caller must ensure that the cast is valid.
+    unsafe { <Self as TraitcastableAnyInfraExt<Target>>::downcast(self).unwrap_unchecked() }
+  }
+}
@@ -0,0 +1,79 @@
+use core::{
+  array::TryFromSliceError,
+  char::{CharTryFromError, ParseCharError},
+  error::Error,
+  fmt::{self, Debug, Display},
+  num::{ParseFloatError, ParseIntError, TryFromIntError},
+  str::{ParseBoolError, Utf8Error},
+};
+
+#[cfg(not(feature = "min_specialization"))]
+use alloc::borrow::Cow;
+use alloc::{
+  boxed::Box,
+  string::{FromUtf8Error, FromUtf16Error, String},
+  vec::Vec,
+};
+
+use crate::{
+  TraitcastableClone, TraitcastableEq, TraitcastableHash, TraitcastablePartialEq,
+  TraitcastablePartialOrd, make_trait_castable_decl,
+};
+
+/// Implements `TraitcastableAny` for the types and for `Vec`, `Box<[T]>` and `Option` of them.
+///
+/// Generic implementations for all `T` can't list targets like `Debug`,
+///  since those would have to depend on the bounds of `T`, which specialization doesn't allow.
+macro_rules! implement_with_containers {
+  ($($ty:ty),+ $(,)? => $targets:tt, containers => $container_targets:tt) => {
+    make_trait_castable_decl! {
+      $(
+        $ty => $targets,
+        Vec<$ty> => $container_targets,
+        Box<[$ty]> => $container_targets,
+        Option<$ty> => $container_targets,
+      )+
+    }
+  };
+}
+
+implement_with_containers! {
+  bool, char, i8, i16, i32, i64, i128, isize, u8, u16, u32, u64, u128, usize, String
+    => (Debug, Display, TraitcastableClone, TraitcastablePartialEq, TraitcastableEq, TraitcastableHash, TraitcastablePartialOrd),
+  containers => (Debug, TraitcastableClone, TraitcastablePartialEq, TraitcastableEq, TraitcastableHash, TraitcastablePartialOrd)
+}
+
+implement_with_containers! {
+  f32, f64
+    => (Debug, Display, TraitcastableClone, TraitcastablePartialEq, TraitcastablePartialOrd),
+  containers => (Debug, TraitcastableClone, TraitcastablePartialEq, TraitcastablePartialOrd)
+}
+
+make_trait_castable_decl! {
+  () => (Debug, TraitcastableClone, TraitcastablePartialEq, TraitcastableEq, TraitcastableHash, TraitcastablePartialOrd),
+  Box<str> => (Debug, Display, TraitcastableClone, TraitcastablePartialEq, TraitcastableEq, TraitcastableHash, TraitcastablePartialOrd),
+
+  fmt::Error => (Debug, Display, Error, TraitcastableClone, TraitcastablePartialEq, TraitcastableEq, TraitcastableHash, TraitcastablePartialOrd),
+  ParseIntError => (Debug, Display, Error, TraitcastableClone, TraitcastablePartialEq, TraitcastableEq),
+  ParseFloatError => (Debug, Display, Error, TraitcastableClone, TraitcastablePartialEq, TraitcastableEq),
+  ParseBoolError => (Debug, Display, Error, TraitcastableClone, TraitcastablePartialEq, TraitcastableEq),
+  ParseCharError => (Debug, Display, Error, TraitcastableClone, TraitcastablePartialEq, TraitcastableEq),
+  TryFromIntError => (Debug, Display, Error, TraitcastableClone, TraitcastablePartialEq, TraitcastableEq),
+  CharTryFromError => (Debug, Display, Error, TraitcastableClone, TraitcastablePartialEq, TraitcastableEq),
+  TryFromSliceError => (Debug, Display, Error, TraitcastableClone),
+  Utf8Error => (Debug, Display, Error, TraitcastableClone, TraitcastablePartialEq, TraitcastableEq),
+  FromUtf8Error => (Debug, Display, Error, TraitcastableClone, TraitcastablePartialEq, TraitcastableEq),
+  FromUtf16Error => (Debug, Display, Error),
+}
+
+// Specializing the blanket implementation of `min_specialization` on `'static` lifetimes is impossible.
+#[cfg(not(feature = "min_specialization"))]
+make_trait_castable_decl! {
+  &'static str => (Debug, Display, TraitcastableClone, TraitcastablePartialEq, TraitcastableEq, TraitcastableHash, TraitcastablePartialOrd),
+  Cow<'static, str> => (Debug, Display, TraitcastableClone, TraitcastablePartialEq, TraitcastableEq, TraitcastableHash, TraitcastablePartialOrd),
+}
+
+#[cfg(feature = "std")]
+make_trait_castable_decl! {
+  std::io::Error => (Debug, Display, Error),
+}
@@ -30,3 +30,1472 @@ fn test_trait_castable() {
   let x: &dyn Print = castable.downcast_ref().unwrap();
   x.print();
 }
+
+#[test]
+fn test_cast_guard() {
+  use crate::TraitcastableGuardExt;
+  use core::cell::{Ref, RefCell};
+
+  let cell: RefCell<Box<dyn TraitcastableAny>> = RefCell::new(Box::new(Source(5)));
+  let guard = Ref::map(cell.borrow(), Box::as_ref);
+  let guard: Result<Ref<'_, dyn Send>, _> = guard.cast_guard();
+  let guard = guard.err().unwrap();
+  let as_print: Ref<'_, dyn Print> = guard.cast_guard().unwrap();
+  assert_eq!(as_print.print(), 5);
+}
+
+#[cfg(feature = "std")]
+#[test]
+fn test_cast_guard_mutex() {
+  use crate::TraitcastableGuardExt;
+  use std::sync::{MappedMutexGuard, Mutex, MutexGuard};
+
+  let mutex: Mutex<Box<dyn TraitcastableAny>> = Mutex::new(Box::new(Source(5)));
+  let guard = MutexGuard::map(mutex.lock().unwrap(), Box::as_mut);
+  let as_print: MappedMutexGuard<'_, dyn Print> = guard.cast_guard().ok().unwrap();
+  assert_eq!(as_print.print(), 5);
+  drop(as_print);
+}
+
+#[test]
+#[expect(
+  clippy::arc_with_non_send_sync,
+  reason = "Casting `Arc`s with `!Send` contents is what is being tested."
+)]
+fn test_arc_downcast_without_send_sync() {
+  use crate::TraitcastableAnyInfraExt;
+  use alloc::{rc::Rc, sync::Arc};
+
+  struct NotSend(Rc<i32>);
+  make_trait_castable_decl! {
+    NotSend => (Print)
+  }
+  impl Print for NotSend {
+    fn print(&self) -> i32 {
+      *self.0
+    }
+  }
+
+  let castable: Arc<dyn TraitcastableAny> = Arc::new(NotSend(Rc::new(7)));
+  let as_print: Arc<dyn Print> = castable.clone().downcast().ok().unwrap();
+  assert_eq!(as_print.print(), 7);
+  let concrete: Arc<NotSend> = castable.downcast().ok().unwrap();
+  assert_eq!(*concrete.0, 7);
+}
+
+#[test]
+fn test_downcast_unique() {
+  use crate::TraitcastableUniqueExt;
+  use alloc::rc::Rc;
+  use core::cell::Cell;
+
+  #[repr(align(64))]
+  struct Aligned(Rc<Cell<i32>>);
+  impl Drop for Aligned {
+    fn drop(&mut self) {
+      self.0.set(self.0.get() + 1);
+    }
+  }
+  impl Print for Aligned {
+    fn print(&self) -> i32 {
+      self.0.get()
+    }
+  }
+  make_trait_castable_decl! {
+    Aligned => (Print),
+  }
+
+  let mut castable: Rc<dyn TraitcastableAny> = Rc::new(Source(3));
+  let shared = castable.clone();
+  assert!(TraitcastableUniqueExt::<dyn Print>::downcast_get_mut(&mut castable).is_none());
+  let castable = TraitcastableUniqueExt::<dyn Print>::try_downcast_unique(castable)
+    .err()
+    .unwrap();
+  drop(shared);
+
+  let mut castable = castable;
+  let source: &mut Source = castable.downcast_get_mut().unwrap();
+  source.0 = 4;
+  let as_print: Box<dyn Print> = castable.try_downcast_unique().ok().unwrap();
+  assert_eq!(as_print.print(), 4);
+
+  // The value is moved out of the allocation without being dropped, even if it is over aligned.
+  let drops = Rc::new(Cell::new(0));
+  let castable: Rc<dyn TraitcastableAny> = Rc::new(Aligned(Rc::clone(&drops)));
+  let as_print: Box<dyn Print> = castable.try_downcast_unique().ok().unwrap();
+  assert_eq!(as_print.print(), 0);
+  drop(as_print);
+  assert_eq!(drops.get(), 1);
+  assert_eq!(Rc::strong_count(&drops), 1);
+}
+
+#[test]
+fn test_stack_box() {
+  use crate::{InlineCastable, StackBox, TraitcastableAnyInfraExt};
+  use core::sync::atomic::{AtomicUsize, Ordering};
+
+  static DROPS: AtomicUsize = AtomicUsize::new(0);
+  struct DropCounter;
+  make_trait_castable_decl! {
+    DropCounter => ()
+  }
+  impl Drop for DropCounter {
+    fn drop(&mut self) {
+      DROPS.fetch_add(1, Ordering::Relaxed);
+    }
+  }
+
+  let castable: InlineCastable<16> = StackBox::new(Source(6));
+  let castable: InlineCastable<16> = TraitcastableAnyInfraExt::<dyn Send>::downcast(castable)
+    .err()
+    .unwrap();
+  let as_print: StackBox<dyn Print, 16> = castable.downcast().ok().unwrap();
+  assert_eq!(as_print.print(), 6);
+
+  let castable: InlineCastable<8> = StackBox::new(DropCounter);
+  let counter: StackBox<DropCounter, 8> = castable.downcast().ok().unwrap();
+  assert_eq!(DROPS.load(Ordering::Relaxed), 0);
+  drop(counter);
+  assert_eq!(DROPS.load(Ordering::Relaxed), 1);
+}
+
+#[test]
+fn test_custom_pointer() {
+  use crate::{TraitcastableAnyInfraExt, TraitcastablePointer};
+
+  struct MyBox<T: ?Sized>(Box<T>);
+  // SAFETY:
+  // `MyBox` is a thin wrapper around `Box`.
+  unsafe impl<T: ?Sized> TraitcastablePointer for MyBox<T> {
+    type Pointee = T;
+    type Rebind<U: ?Sized + 'static> = MyBox<U>;
+
+    fn into_raw(this: Self) -> *mut T {
+      Box::into_raw(this.0)
+    }
+    unsafe fn from_raw(raw: *mut T) -> Self {
+      // SAFETY: Guaranteed by the caller.
+      Self(unsafe { Box::from_raw(raw) })
+    }
+  }
+
+  let castable: MyBox<dyn TraitcastableAny> = MyBox(Box::new(Source(8)));
+  let as_print: MyBox<dyn Print> = castable.downcast().ok().unwrap();
+  assert_eq!(as_print.0.print(), 8);
+}
+
+#[test]
+fn test_any_registry() {
+  use crate::AnyRegistry;
+  use core::any::Any;
+
+  let mut registry = AnyRegistry::new();
+  registry.register::<Source>();
+
+  let any: Box<dyn Any> = Box::new(Source(9));
+  let castable = registry.from_any_ref(any.as_ref()).unwrap();
+  let as_print: &dyn Print = castable.downcast_ref().unwrap();
+  assert_eq!(as_print.print(), 9);
+
+  let castable = registry.from_any_box(any).ok().unwrap();
+  let source: &Source = castable.downcast_ref().unwrap();
+  assert_eq!(source.0, 9);
+
+  let any: Box<dyn Any> = Box::new(1_u8);
+  assert!(registry.from_any_box(any).is_err());
+
+  // The `Send` and `Sync` markers are kept.
+  let mut any: Box<dyn Any + Send + Sync> = Box::new(Source(4));
+  let castable: &mut (dyn TraitcastableAny + Send + Sync) =
+    registry.from_any_mut(any.as_mut()).unwrap();
+  let source: &mut Source = castable.downcast_mut().unwrap();
+  source.0 += 1;
+  let any: Box<dyn Any + Send> = any;
+  let castable: Box<dyn TraitcastableAny + Send> = registry.from_any_box(any).ok().unwrap();
+  let source: &Source = castable.downcast_ref().unwrap();
+  assert_eq!(source.0, 5);
+}
+
+#[test]
+fn test_try_clone_box() {
+  use crate::{TraitcastableClone, TraitcastableCloneExt};
+
+  #[derive(Clone)]
+  struct Clonable(i32);
+  make_trait_castable_decl! {
+    Clonable => (TraitcastableClone)
+  }
+
+  let castable: Box<dyn TraitcastableAny + Send + Sync> = Box::new(Clonable(10));
+  let cloned = castable.try_clone_box().unwrap();
+  let clonable: &Clonable = cloned.downcast_ref().unwrap();
+  assert_eq!(clonable.0, 10);
+
+  let not_clonable: Box<dyn TraitcastableAny> = Box::new(Source(1));
+  assert!(not_clonable.try_clone_box().is_none());
+}
+
+#[test]
+fn test_dyn_key() {
+  use crate::{
+    DynKey, TraitcastableEq, TraitcastableHash, TraitcastablePartialEq, TraitcastablePartialOrd,
+  };
+  use alloc::vec::Vec;
+  use core::{
+    cmp::Ordering,
+    hash::{Hash, Hasher},
+  };
+
+  /// Records the hashed bytes, so equal hashes can be compared.
+  #[derive(Default)]
+  struct Recorder(Vec<u8>);
+  impl Hasher for Recorder {
+    fn finish(&self) -> u64 {
+      0
+    }
+    fn write(&mut self, bytes: &[u8]) {
+      self.0.extend_from_slice(bytes);
+    }
+  }
+  fn hashed(key: &DynKey<&dyn TraitcastableAny>) -> Vec<u8> {
+    let mut recorder = Recorder::default();
+    key.hash(&mut recorder);
+    recorder.0
+  }
+
+  #[derive(PartialEq, Eq, Hash, PartialOrd)]
+  struct Id(u32);
+  struct Wrapper(Id);
+  make_trait_castable_decl! {
+    Id => (TraitcastablePartialEq, TraitcastableEq, TraitcastableHash, TraitcastablePartialOrd),
+    Wrapper => () delegate 0,
+  }
+
+  let one: Box<dyn TraitcastableAny> = Box::new(Id(1));
+  let other_one: Box<dyn TraitcastableAny> = Box::new(Id(1));
+  let two: Box<dyn TraitcastableAny> = Box::new(Id(2));
+  let source: Box<dyn TraitcastableAny> = Box::new(Source(1));
+  assert!(DynKey(&*one) == DynKey(&*other_one));
+  assert!(DynKey(&*one) != DynKey(&*two));
+  assert_eq!(hashed(&DynKey(&*one)), hashed(&DynKey(&*other_one)));
+  assert_ne!(hashed(&DynKey(&*one)), hashed(&DynKey(&*two)));
+  assert_eq!(
+    DynKey(&*one).partial_cmp(&DynKey(&*two)),
+    Some(Ordering::Less)
+  );
+  // Values of different concrete types are never equal, but ordered by their type.
+  assert!(DynKey(&*one) != DynKey(&*source));
+  assert!(DynKey(&*one).partial_cmp(&DynKey(&*source)).is_some());
+
+  // The targets of a delegate don't compare the wrapper, so it is only equal to itself.
+  let wrapper: Box<dyn TraitcastableAny> = Box::new(Wrapper(Id(1)));
+  let other_wrapper: Box<dyn TraitcastableAny> = Box::new(Wrapper(Id(2)));
+  assert!(DynKey(&*wrapper) == DynKey(&*wrapper));
+  assert!(DynKey(&*wrapper) != DynKey(&*other_wrapper));
+  assert_eq!(hashed(&DynKey(&*wrapper)), hashed(&DynKey(&*other_wrapper)));
+  assert_eq!(
+    DynKey(&*wrapper).partial_cmp(&DynKey(&*other_wrapper)),
+    None
+  );
+}
+
+#[test]
+fn test_dyn_key_eq() {
+  use crate::{DynKey, TraitcastableEq, TraitcastablePartialEq, TraitcastablePartialOrd};
+  use core::cmp::Ordering;
+
+  #[derive(PartialEq, PartialOrd)]
+  struct Float(f64);
+  #[derive(PartialEq, Eq, PartialOrd)]
+  struct Id(u32);
+  make_trait_castable_decl! {
+    Float => (TraitcastablePartialEq, TraitcastablePartialOrd),
+    Id => (TraitcastablePartialEq, TraitcastableEq),
+  }
+
+  // Without `Eq` values are only equal to themselves, even if they are NaN.
+  let nan: Box<dyn TraitcastableAny> = Box::new(Float(f64::NAN));
+  assert!(DynKey(&*nan) == DynKey(&*nan));
+  let one: Box<dyn TraitcastableAny> = Box::new(Float(1.0));
+  let other_one: Box<dyn TraitcastableAny> = Box::new(Float(1.0));
+  assert!(DynKey(&*one) != DynKey(&*other_one));
+  assert_eq!(DynKey(&*one).partial_cmp(&DynKey(&*other_one)), None);
+  assert_eq!(
+    DynKey(&*one).partial_cmp(&DynKey(&*one)),
+    Some(Ordering::Equal)
+  );
+
+  let id: Box<dyn TraitcastableAny> = Box::new(Id(1));
+  let other_id: Box<dyn TraitcastableAny> = Box::new(Id(1));
+  assert!(DynKey(&*id) == DynKey(&*other_id));
+}
+
+#[test]
+fn test_cast_erased() {
+  use core::any::TypeId;
+
+  let mut castable: Box<dyn TraitcastableAny> = Box::new(Source(11));
+  assert!(castable.cast_erased(TypeId::of::<dyn Send>()).is_none());
+
+  let erased = castable.cast_erased(TypeId::of::<dyn Print>()).unwrap();
+  assert!(erased.downcast::<dyn Send>().is_none());
+  assert_eq!(erased.downcast::<dyn Print>().unwrap().print(), 11);
+
+  let erased = castable.cast_erased_mut(TypeId::of::<dyn Print>()).unwrap();
+  let erased = erased.downcast::<dyn Send>().err().unwrap();
+  assert_eq!(erased.downcast::<dyn Print>().ok().unwrap().print(), 11);
+}
+
+#[test]
+fn test_find_target_by_name() {
+  use alloc::vec::Vec;
+
+  let castable: Box<dyn TraitcastableAny> = Box::new(Source(12));
+  let names: Vec<_> = castable.target_names().collect();
+  assert_eq!(names, ["dyn trait_cast::test::Print"]);
+
+  for name in [
+    "dyn trait_cast::test::Print",
+    "trait_cast::test::Print",
+    "Print",
+  ] {
+    let erased = castable.find_target_by_name(name).unwrap();
+    assert_eq!(erased.downcast::<dyn Print>().unwrap().print(), 12);
+  }
+  assert!(castable.find_target_by_name("test::Print").is_none());
+  assert!(castable.find_target_by_name("Renderer").is_none());
+}
+
+#[test]
+fn test_cast_stable() {
+  use crate::{StableId, StableTypeId};
+
+  struct Plugin(i32);
+  trait Greet {
+    fn greet(&self) -> i32;
+  }
+  impl Greet for Plugin {
+    fn greet(&self) -> i32 {
+      self.0
+    }
+  }
+  impl StableTypeId for dyn Greet {
+    const STABLE_ID: StableId = StableId::from_name("trait_cast.test.Greet");
+  }
+  make_trait_castable_decl! {
+    Plugin => (Print, #[stable] Greet),
+  }
+  impl Print for Plugin {
+    fn print(&self) -> i32 {
+      -self.0
+    }
+  }
+
+  let mut castable: Box<dyn TraitcastableAny> = Box::new(Plugin(3));
+  let greet_id = <dyn Greet as StableTypeId>::STABLE_ID;
+  assert_eq!(
+    castable
+      .cast_stable(greet_id)
+      .unwrap()
+      .target()
+      .target_stable_id(),
+    Some(greet_id)
+  );
+  // SAFETY: `dyn Greet` is the only type with its `StableId`.
+  let greet = unsafe { castable.downcast_ref_stable::<dyn Greet>() }.unwrap();
+  assert_eq!(greet.greet(), 3);
+  // SAFETY: See above.
+  assert!(unsafe { castable.downcast_mut_stable::<dyn Greet>() }.is_some());
+  // Targets without `#[stable]` can only be found by `TypeId`.
+  assert!(castable.cast_stable(StableId::from_name("Print")).is_none());
+  let print: &dyn Print = castable.downcast_ref().unwrap();
+  assert_eq!(print.print(), -3);
+}
+
+#[test]
+fn test_stable_id_registry_duplicates() {
+  use crate::{DuplicateStableIdError, StableId, StableIdRegistry};
+
+  let first = StableId::from_raw(1);
+  let second = StableId::from_raw(2);
+  let mut registry = StableIdRegistry::new();
+  registry
+    .register_target_with_id::<dyn Print>(first)
+    .unwrap();
+  // Registering the same target with the same id again is fine.
+  registry
+    .register_target_with_id::<dyn Print>(first)
+    .unwrap();
+  assert_eq!(
+    registry
+      .register_target_with_id::<dyn TraitcastableAny>(first)
+      .unwrap_err(),
+    DuplicateStableIdError::IdTaken {
+      id: first,
+      existing_type_name: core::any::type_name::<dyn Print>(),
+      type_name: core::any::type_name::<dyn TraitcastableAny>(),
+    }
+  );
+  assert_eq!(
+    registry
+      .register_target_with_id::<dyn Print>(second)
+      .unwrap_err(),
+    DuplicateStableIdError::TypeRegistered {
+      type_name: core::any::type_name::<dyn Print>(),
+      existing_id: first,
+      id: second,
+    }
+  );
+  assert_eq!(registry.target_type_id(second), None);
+}
+
+#[test]
+fn test_trait_map() {
+  use crate::{Composite, TraitMap};
+  use alloc::vec::Vec;
+
+  trait Tick {
+    fn tick(&mut self);
+  }
+  impl Tick for Source {
+    fn tick(&mut self) {
+      self.0 += 1;
+    }
+  }
+  struct Ticker(i32);
+  impl Tick for Ticker {
+    fn tick(&mut self) {
+      self.0 -= 1;
+    }
+  }
+  make_trait_castable_decl! {
+    Ticker => (Tick),
+  }
+  // `Source` is only castable to `Print`, so a wrapper adds `Tick`.
+  struct TickingSource(Source);
+  impl Tick for TickingSource {
+    fn tick(&mut self) {
+      self.0.tick();
+    }
+  }
+  impl Print for TickingSource {
+    fn print(&self) -> i32 {
+      self.0.print()
+    }
+  }
+  make_trait_castable_decl! {
+    TickingSource => (Tick, Print),
+  }
+
+  let mut map = TraitMap::new();
+  let source = map.insert(Box::new(Source(1)));
+  let ticker = map.insert(Box::new(Ticker(10)));
+  let ticking_source = map.insert(Box::new(TickingSource(Source(100))));
+  assert_eq!(map.len(), 3);
+  assert_eq!(map.count::<dyn Tick>(), 2);
+  assert_eq!(map.count::<dyn Print>(), 2);
+
+  for (_, tick) in map.iter_mut::<dyn Tick>() {
+    tick.tick();
+  }
+  let printed: Vec<_> = map.iter::<dyn Print>().map(|(_, p)| p.print()).collect();
+  assert_eq!(printed, [1, 101]);
+  assert_eq!(map.get::<Ticker>().unwrap().0, 9);
+  map.get_mut::<Ticker>().unwrap().0 = 20;
+  assert_eq!(map.cast::<Ticker>(ticker).unwrap().0, 20);
+  assert!(map.cast::<dyn Tick>(source).is_none());
+
+  let both: Vec<_> = map
+    .iter_both::<dyn Tick, dyn Print>()
+    .map(|(key, _, print)| (key, print.print()))
+    .collect();
+  assert_eq!(both, [(ticking_source, 101)]);
+
+  // Removed handles stay invalid, even if the slot is reused.
+  assert!(map.remove(ticker).is_some());
+  assert!(map.remove(ticker).is_none());
+  assert!(map.get::<Ticker>().is_none());
+  assert_eq!(map.count::<dyn Tick>(), 1);
+  let new_ticker = map.insert(Box::new(Ticker(5)));
+  assert!(!map.contains_key(ticker));
+  assert_eq!(map.cast::<dyn Tick>(new_ticker).map(|_| ()), Some(()));
+  assert_eq!(map.objects().count(), 3);
+
+  // The targets of a `Composite` change with its components, so it has to be reindexed.
+  let composite = map.insert(Box::new(Composite::new()));
+  map
+    .cast_mut::<Composite>(composite)
+    .unwrap()
+    .add(Box::new(Ticker(1)));
+  assert_eq!(map.count::<dyn Tick>(), 2);
+  assert!(map.reindex(composite));
+  assert_eq!(map.count::<dyn Tick>(), 3);
+  assert!(map.remove(composite).is_some());
+  assert!(!map.reindex(composite));
+  assert_eq!(map.count::<dyn Tick>(), 2);
+}
+
+#[test]
+fn test_service_container() {
+  use crate::{ServiceContainer, ServiceError};
+  use alloc::{string::String, sync::Arc};
+
+  trait Logger: Send + Sync {
+    fn log(&self) -> &'static str;
+  }
+  trait Flush: Send + Sync {}
+  trait Unregistered: Send + Sync {}
+  struct ConsoleLogger;
+  impl Logger for ConsoleLogger {
+    fn log(&self) -> &'static str {
+      "console"
+    }
+  }
+  impl Flush for ConsoleLogger {}
+  make_trait_castable_decl! {
+    ConsoleLogger => (Logger, Flush),
+  }
+  trait Repository: Send + Sync {
+    fn logger(&self) -> &dyn Logger;
+  }
+  struct Database(Arc<dyn Logger>);
+  impl Repository for Database {
+    fn logger(&self) -> &dyn Logger {
+      self.0.as_ref()
+    }
+  }
+  make_trait_castable_decl! {
+    Database => (Repository),
+  }
+  struct Pool(ConsoleLogger);
+  make_trait_castable_decl! {
+    Pool => () delegate 0,
+  }
+
+  let mut container = ServiceContainer::new();
+  container.register(Arc::new(ConsoleLogger));
+  container
+    .register_factory::<dyn Repository>(|container| Ok(Arc::new(Database(container.resolve()?))));
+
+  // The same object is reachable through every target.
+  let logger = container.resolve::<dyn Logger>().unwrap();
+  let flush = container.resolve::<dyn Flush>().unwrap();
+  assert!(core::ptr::addr_eq(
+    Arc::as_ptr(&logger),
+    Arc::as_ptr(&flush)
+  ));
+  let repository = container.resolve::<dyn Repository>().unwrap();
+  assert_eq!(repository.logger().log(), "console");
+  // Lazy services are only created once.
+  let again = container.resolve::<dyn Repository>().unwrap();
+  assert!(Arc::ptr_eq(&repository, &again));
+  assert_eq!(
+    container.resolve::<dyn Unregistered>().err(),
+    Some(ServiceError::NotFound {
+      type_name: core::any::type_name::<dyn Unregistered>(),
+      name: None
+    })
+  );
+
+  container
+    .register_named("file", Arc::new(ConsoleLogger))
+    .unwrap();
+  assert!(matches!(
+    container.resolve::<dyn Logger>(),
+    Err(ServiceError::Ambiguous { count: 2, .. })
+  ));
+  assert_eq!(container.resolve_all::<dyn Logger>().unwrap().len(), 2);
+  assert!(container.resolve_named::<dyn Logger>("file").is_ok());
+  assert!(container.resolve_named::<dyn Repository>("file").is_err());
+  assert_eq!(
+    container
+      .register_named("file", Arc::new(ConsoleLogger))
+      .err(),
+    Some(ServiceError::DuplicateName {
+      name: String::from("file")
+    })
+  );
+
+  // Cycles are detected.
+  let mut container = ServiceContainer::new();
+  container.register_factory::<dyn Logger>(|container| {
+    container.resolve::<dyn Repository>()?;
+    Ok(Arc::new(ConsoleLogger))
+  });
+  container
+    .register_factory::<dyn Repository>(|container| Ok(Arc::new(Database(container.resolve()?))));
+  assert!(matches!(
+    container.resolve::<dyn Repository>(),
+    Err(ServiceError::Cycle { .. })
+  ));
+
+  // Targets of a delegate can't be resolved, so they don't make resolving ambiguous.
+  let mut container = ServiceContainer::new();
+  container.register(Arc::new(Pool(ConsoleLogger)));
+  assert!(matches!(
+    container.resolve::<dyn Logger>(),
+    Err(ServiceError::NotFound { .. })
+  ));
+  container.register(Arc::new(ConsoleLogger));
+  assert_eq!(container.resolve::<dyn Logger>().unwrap().log(), "console");
+  assert_eq!(container.resolve_all::<dyn Logger>().unwrap().len(), 1);
+}
+
+#[test]
+fn test_event_bus() {
+  use crate::EventBus;
+  use alloc::sync::Arc;
+  use core::sync::atomic::{AtomicI32, Ordering};
+
+  trait DamageEvent {
+    fn damage(&self) -> i32;
+  }
+  struct Explosion(i32);
+  impl DamageEvent for Explosion {
+    fn damage(&self) -> i32 {
+      self.0
+    }
+  }
+  struct Fall(i32);
+  impl DamageEvent for Fall {
+    fn damage(&self) -> i32 {
+      self.0 / 2
+    }
+  }
+  // A dud only counts as damage once it is armed.
+  struct Dud(i32, bool);
+  impl Dud {
+    const fn is_armed(&self) -> bool {
+      self.1
+    }
+  }
+  impl DamageEvent for Dud {
+    fn damage(&self) -> i32 {
+      self.0
+    }
+  }
+  make_trait_castable_decl! {
+    Explosion => (DamageEvent),
+    Fall => (DamageEvent),
+    Dud => () if Self::is_armed => (DamageEvent),
+  }
+
+  let total = Arc::new(AtomicI32::new(0));
+  let explosions = Arc::new(AtomicI32::new(0));
+  let mut bus = EventBus::new();
+  let total_handler = Arc::clone(&total);
+  let damage = bus.subscribe::<dyn DamageEvent>(move |event| {
+    total_handler.fetch_add(event.damage(), Ordering::Relaxed);
+  });
+  let explosions_handler = Arc::clone(&explosions);
+  bus.subscribe::<Explosion>(move |_| {
+    explosions_handler.fetch_add(1, Ordering::Relaxed);
+  });
+
+  assert_eq!(bus.publish(&Explosion(10)), 2);
+  assert_eq!(bus.publish(&Fall(6)), 1);
+  assert_eq!(bus.publish(&Source(1)), 0);
+  // The handler of a target rejected by its guard isn't called and not counted.
+  assert_eq!(bus.publish(&Dud(100, false)), 0);
+  assert_eq!(total.load(Ordering::Relaxed), 13);
+  assert_eq!(bus.publish(&Dud(2, true)), 1);
+  assert_eq!(total.load(Ordering::Relaxed), 15);
+  assert_eq!(explosions.load(Ordering::Relaxed), 1);
+
+  bus.enqueue(Box::new(Fall(4)));
+  bus.enqueue(Box::new(Explosion(1)));
+  assert_eq!(bus.queued(), 2);
+  assert_eq!(total.load(Ordering::Relaxed), 15);
+  assert_eq!(bus.drain(), 3);
+  assert_eq!(bus.queued(), 0);
+  assert_eq!(total.load(Ordering::Relaxed), 18);
+
+  assert!(bus.unsubscribe(damage));
+  assert!(!bus.unsubscribe(damage));
+  assert_eq!(bus.publish(&Fall(2)), 0);
+}
+
+#[test]
+fn test_dispatch_table() {
+  use crate::DispatchTable;
+
+  trait Flammable {}
+  trait Fire {}
+  struct Wood;
+  impl Flammable for Wood {}
+  struct Torch;
+  impl Fire for Torch {}
+  impl Flammable for Torch {}
+  struct Lava;
+  impl Fire for Lava {}
+  make_trait_castable_decl! {
+    Wood => (Flammable),
+    Torch => (Fire, Flammable),
+    Lava => (Fire),
+  }
+
+  let mut table = DispatchTable::new();
+  table
+    .register::<dyn Flammable, dyn Fire>(|_, _| "burns")
+    .register::<Wood, Lava>(|_, _| "wood meets lava")
+    .register::<dyn Fire, dyn Fire>(|_, _| "fires merge")
+    .register::<Wood, dyn Fire>(|_, _| "wood meets fire");
+
+  // Concrete types beat traits, the matching is symmetric.
+  assert_eq!(table.dispatch(&Wood, &Lava), Some("wood meets lava"));
+  assert_eq!(table.dispatch(&Lava, &Wood), Some("wood meets lava"));
+  assert_eq!(table.dispatch(&Wood, &Torch), Some("wood meets fire"));
+  // Registration order breaks ties between equally specific handlers.
+  assert_eq!(table.dispatch(&Torch, &Torch), Some("burns"));
+  assert_eq!(table.dispatch(&Lava, &Torch), Some("burns"));
+  assert_eq!(table.dispatch(&Lava, &Lava), Some("fires merge"));
+  assert_eq!(table.dispatch(&Wood, &Wood), None);
+  assert!(!table.can_dispatch(&Wood, &Source(1)));
+
+  // The arguments are passed in the order of the registered pair.
+  let mut table = DispatchTable::new();
+  table.register::<Source, dyn Fire>(|source, _| source.0);
+  assert_eq!(table.dispatch(&Lava, &Source(7)), Some(7));
+}
+
+#[test]
+#[expect(
+  clippy::arc_with_non_send_sync,
+  reason = "The adapted targets are not `Send` and `Sync`."
+)]
+fn test_adapter_registry() {
+  use crate::{Adapted, AdapterRegistry};
+  use alloc::{
+    format,
+    string::{String, ToString},
+    sync::Arc,
+  };
+
+  trait RendererV1 {
+    fn draw(&self) -> String;
+  }
+  trait RendererV2 {
+    fn render(&self, scale: u32) -> String;
+  }
+  struct OldWidget;
+  impl RendererV1 for OldWidget {
+    fn draw(&self) -> String {
+      "old".to_string()
+    }
+  }
+  struct NewWidget;
+  impl RendererV2 for NewWidget {
+    fn render(&self, scale: u32) -> String {
+      format!("new x{scale}")
+    }
+  }
+  make_trait_castable_decl! {
+    OldWidget => (RendererV1),
+    NewWidget => (RendererV2),
+  }
+  struct V1Adapter<P>(P);
+  impl<P: core::ops::Deref<Target = dyn RendererV1>> RendererV2 for V1Adapter<P> {
+    fn render(&self, scale: u32) -> String {
+      format!("{} x{scale}", self.0.draw())
+    }
+  }
+  struct Snapshot(String);
+  impl RendererV2 for Snapshot {
+    fn render(&self, scale: u32) -> String {
+      format!("{} x{scale}", self.0)
+    }
+  }
+
+  let mut registry = AdapterRegistry::new();
+  registry
+    .register::<dyn RendererV1, dyn RendererV2>(|old| Box::new(Snapshot(old.draw())))
+    .register_box::<dyn RendererV1, dyn RendererV2>(|old| Box::new(V1Adapter(old)))
+    .register_arc::<dyn RendererV1, dyn RendererV2>(|old| Arc::new(V1Adapter(old)));
+
+  let new: &dyn TraitcastableAny = &NewWidget;
+  let rendered = registry.cast_or_adapt::<dyn RendererV2>(new).unwrap();
+  assert!(matches!(rendered, Adapted::Borrowed(_)));
+  assert_eq!(rendered.render(1), "new x1");
+
+  let old: &dyn TraitcastableAny = &OldWidget;
+  let adapted = registry.cast_or_adapt::<dyn RendererV2>(old).unwrap();
+  assert!(adapted.is_adapted());
+  assert_eq!(adapted.render(2), "old x2");
+  assert!(registry.cast_or_adapt::<dyn Print>(old).is_none());
+
+  let boxed = registry
+    .cast_or_adapt_box::<dyn RendererV2>(Box::new(OldWidget))
+    .ok()
+    .unwrap();
+  assert_eq!(boxed.render(3), "old x3");
+  let arc = registry
+    .cast_or_adapt_arc::<dyn RendererV2>(Arc::new(OldWidget))
+    .ok()
+    .unwrap();
+  assert_eq!(arc.render(4), "old x4");
+  let source = registry
+    .cast_or_adapt_box::<dyn RendererV2>(Box::new(Source(1)))
+    .err()
+    .unwrap();
+  let source: &Source = source.downcast_ref().unwrap();
+  assert_eq!(source.0, 1);
+}
+
+#[test]
+fn test_composite() {
+  use crate::{Composite, ConflictPolicy, TraitcastableAnyInfraExt};
+
+  trait Damageable {
+    fn damage(&mut self, amount: i32);
+    fn health(&self) -> i32;
+  }
+  struct Health(i32);
+  impl Damageable for Health {
+    fn damage(&mut self, amount: i32) {
+      self.0 -= amount;
+    }
+    fn health(&self) -> i32 {
+      self.0
+    }
+  }
+  impl Print for Health {
+    fn print(&self) -> i32 {
+      -self.0
+    }
+  }
+  make_trait_castable_decl! {
+    Health => (Damageable, Print),
+  }
+
+  let mut composite = Composite::new();
+  let source = composite.add(Box::new(Source(1)));
+  let health = composite.add(Box::new(Health(10)));
+  assert_eq!(composite.len(), 2);
+  assert_eq!(composite.provider::<dyn Print>(), Some(source));
+  assert_eq!(composite.provider::<dyn Damageable>(), Some(health));
+
+  let entity: &mut dyn TraitcastableAny = &mut composite;
+  let print: &dyn Print = entity.downcast_ref().unwrap();
+  assert_eq!(print.print(), 1);
+  let damageable: &mut dyn Damageable = entity.downcast_mut().unwrap();
+  damageable.damage(3);
+  assert_eq!(entity.target_names().count(), 2);
+  assert_eq!(
+    entity
+      .find_target_by_name("Damageable")
+      .unwrap()
+      .downcast::<dyn Damageable>()
+      .unwrap()
+      .health(),
+    7
+  );
+  assert_eq!(composite.get::<Health>().unwrap().0, 7);
+
+  // The remaining component provides the traits of a removed one.
+  let removed = composite.remove(source).unwrap();
+  assert!(!composite.contains(source));
+  assert!(composite.remove(source).is_none());
+  let removed: &Source = removed.downcast_ref().unwrap();
+  assert_eq!(removed.0, 1);
+  assert_eq!(composite.provider::<dyn Print>(), Some(health));
+  let entity: &dyn TraitcastableAny = &composite;
+  let print: &dyn Print = entity.downcast_ref().unwrap();
+  assert_eq!(print.print(), -7);
+
+  let mut last = Composite::with_policy(ConflictPolicy::Last);
+  last.add(Box::new(Source(1)));
+  let mut inner = Composite::new();
+  inner.add(Box::new(Source(2)));
+  last.add(Box::new(inner));
+  let entity: &dyn TraitcastableAny = &last;
+  let print: &dyn Print = entity.downcast_ref().unwrap();
+  assert_eq!(print.print(), 2);
+
+  // The box owns the composite and not the component.
+  let boxed: Box<dyn TraitcastableAny> = Box::new(last);
+  let boxed = TraitcastableAnyInfraExt::<dyn Print>::downcast(boxed)
+    .err()
+    .unwrap();
+  let entity: &dyn TraitcastableAny = boxed.as_ref();
+  assert!(TraitcastableAnyInfra::<dyn Print>::can_be(entity));
+}
+
+#[test]
+fn test_scoped_targets() {
+  use alloc::format;
+
+  mod auth {
+    pub struct Key(());
+    pub const fn key() -> Key {
+      Key(())
+    }
+  }
+  struct OtherKey;
+  trait AdminControl {
+    fn level(&self) -> i32;
+  }
+  struct Panel(i32);
+  impl Print for Panel {
+    fn print(&self) -> i32 {
+      self.0
+    }
+  }
+  impl AdminControl for Panel {
+    fn level(&self) -> i32 {
+      self.0 * 10
+    }
+  }
+  make_trait_castable_decl! {
+    Panel => (Print) in auth::Key => (AdminControl),
+  }
+
+  let mut panel: Box<dyn TraitcastableAny> = Box::new(Panel(2));
+  let admin: Option<&dyn AdminControl> = panel.downcast_ref();
+  assert!(admin.is_none());
+  assert!(
+    panel
+      .cast_erased(core::any::TypeId::of::<dyn AdminControl>())
+      .is_none()
+  );
+  assert!(!panel.can_be_scoped::<dyn AdminControl, _>(&OtherKey));
+  assert!(panel.can_be_scoped::<dyn AdminControl, _>(&auth::key()));
+  assert!(
+    panel
+      .downcast_ref_scoped::<dyn Print, _>(&auth::key())
+      .is_none()
+  );
+
+  let admin: &dyn AdminControl = panel.downcast_ref_scoped(&auth::key()).unwrap();
+  assert_eq!(admin.level(), 20);
+  let admin: &mut dyn AdminControl = panel.downcast_mut_scoped(&auth::key()).unwrap();
+  assert_eq!(admin.level(), 20);
+  assert_eq!(
+    format!("{panel:?}"),
+    "TraitcastableAny to {dyn trait_cast::test::Print}"
+  );
+}
+
+#[test]
+fn test_provide() {
+  use crate::{Request, TraitcastableAnyInfraExt, request_ref};
+  use core::cell::{Cell, OnceCell};
+
+  struct Helper(i32);
+  impl Print for Helper {
+    fn print(&self) -> i32 {
+      self.0
+    }
+  }
+  trait Describe {
+    fn describe(&self) -> i32;
+  }
+  impl Describe for Helper {
+    fn describe(&self) -> i32 {
+      -self.0
+    }
+  }
+  struct Lazy {
+    helper: OnceCell<Helper>,
+    created: Cell<u32>,
+  }
+  // SAFETY:
+  // No targets are returned, the casts are resolved by `provide`.
+  unsafe impl TraitcastableAny for Lazy {
+    fn traitcast_targets(&self) -> &[crate::TraitcastTarget] {
+      &[]
+    }
+    fn provide<'a>(&'a self, request: &mut Request<'a>) {
+      if !request.would_be_satisfied_by_ref_of::<dyn Print>()
+        && !request.would_be_satisfied_by_ref_of::<dyn Describe>()
+      {
+        return;
+      }
+      let helper = self.helper.get_or_init(|| {
+        self.created.set(self.created.get() + 1);
+        Helper(3)
+      });
+      request
+        .provide_ref::<dyn Print>(helper)
+        .provide_ref::<dyn Describe>(helper)
+        .provide_ref::<dyn Describe>(&Helper(4));
+    }
+  }
+
+  let lazy: Box<dyn TraitcastableAny> = Box::new(Lazy {
+    helper: OnceCell::new(),
+    created: Cell::new(0),
+  });
+  assert!(TraitcastableAnyInfra::<dyn Print>::can_be(lazy.as_ref()));
+  assert!(!TraitcastableAnyInfra::<dyn Send>::can_be(lazy.as_ref()));
+  let print: &dyn Print = lazy.downcast_ref().unwrap();
+  assert_eq!(print.print(), 3);
+  let describe: &dyn Describe = request_ref(lazy.as_ref()).unwrap();
+  assert_eq!(describe.describe(), -3);
+  let lazy_ref: &Lazy = lazy.downcast_ref().unwrap();
+  assert_eq!(lazy_ref.created.get(), 1);
+
+  // The provided values are not the object itself, so the box can't be cast to them.
+  let lazy = TraitcastableAnyInfraExt::<dyn Print>::downcast(lazy)
+    .err()
+    .unwrap();
+  assert!(TraitcastableAnyInfra::<dyn Print>::downcast_ref_in_place(lazy.as_ref()).is_none());
+}
+
+#[test]
+fn test_field_targets() {
+  use crate::{TraitcastableAnyInfraExt, TraitcastableUniqueExt};
+  use alloc::rc::Rc;
+
+  trait Render {
+    fn render(&self) -> i32;
+  }
+  trait Count {
+    fn increment(&mut self) -> i32;
+  }
+  struct Canvas(i32);
+  impl Render for Canvas {
+    fn render(&self) -> i32 {
+      self.0
+    }
+  }
+  struct Counter(i32);
+  impl Count for Counter {
+    fn increment(&mut self) -> i32 {
+      self.0 += 1;
+      self.0
+    }
+  }
+  struct Logged {
+    lines: i32,
+    canvas: Canvas,
+    counter: Counter,
+    inner: Source,
+  }
+  impl Print for Logged {
+    fn print(&self) -> i32 {
+      self.lines
+    }
+  }
+  struct Pair(i32, Counter);
+  make_trait_castable_decl! {
+    Logged => () expose canvas => (Render) expose counter => (Count) delegate inner,
+    Pair => () expose 1 => (Count),
+  }
+
+  let mut logged: Box<dyn TraitcastableAny> = Box::new(Logged {
+    lines: 7,
+    canvas: Canvas(1),
+    counter: Counter(2),
+    inner: Source(3),
+  });
+  let render: &dyn Render = logged.downcast_ref().unwrap();
+  assert_eq!(render.render(), 1);
+  let count: &mut dyn Count = logged.downcast_mut().unwrap();
+  assert_eq!(count.increment(), 3);
+  let erased = logged.find_target_by_name("Render").unwrap();
+  assert_eq!(erased.downcast::<dyn Render>().unwrap().render(), 1);
+  assert!(erased.target().projection().is_some());
+
+  // The delegate is cast to `Print`, since `Logged` itself has no such target.
+  let print: &dyn Print = logged.downcast_ref().unwrap();
+  assert_eq!(print.print(), 3);
+  // Targets of the delegate can't be found by name.
+  assert!(logged.find_target_by_name("Print").is_none());
+  assert!(
+    logged
+      .cast_erased(core::any::TypeId::of::<dyn Print>())
+      .is_some()
+  );
+  let concrete: &Logged = logged.downcast_ref().unwrap();
+  assert_eq!(concrete.print(), 7);
+
+  // Owned casts can't point to a field.
+  let logged = TraitcastableAnyInfraExt::<dyn Render>::downcast(logged)
+    .err()
+    .unwrap();
+  let logged = TraitcastableAnyInfraExt::<dyn Print>::downcast(logged)
+    .err()
+    .unwrap();
+  let logged: Rc<dyn TraitcastableAny> = Rc::from(logged);
+  assert!(TraitcastableUniqueExt::<dyn Count>::try_downcast_unique(logged).is_err());
+
+  let mut pair: Box<dyn TraitcastableAny> = Box::new(Pair(10, Counter(20)));
+  let count: &mut dyn Count = pair.downcast_mut().unwrap();
+  assert_eq!(count.increment(), 21);
+  let pair: &Pair = pair.downcast_ref().unwrap();
+  assert_eq!(pair.0, 10);
+}
+
+#[test]
+fn test_delegate_at_offset_zero() {
+  use crate::{
+    StackBox, TraitcastableAnyInfraExt, TraitcastableClone, TraitcastableCloneExt,
+    TraitcastableUniqueExt,
+  };
+  use alloc::rc::Rc;
+
+  #[derive(Clone)]
+  struct Inner(i32);
+  impl Print for Inner {
+    fn print(&self) -> i32 {
+      self.0
+    }
+  }
+  #[derive(Clone)]
+  #[repr(C)]
+  struct Wrapper {
+    inner: Inner,
+    extra: u64,
+  }
+  #[derive(Clone)]
+  #[repr(C)]
+  struct CloneWrapper {
+    inner: Inner,
+    extra: u64,
+  }
+  make_trait_castable_decl! {
+    Inner => (Print, TraitcastableClone),
+    Wrapper => (TraitcastableClone) delegate inner,
+    CloneWrapper => () delegate inner,
+  }
+
+  let wrapper: Box<dyn TraitcastableAny> = Box::new(Wrapper {
+    inner: Inner(1),
+    extra: 2,
+  });
+  // The delegate is stored at the address of the wrapper, but the owned casts must still fail.
+  let print: &dyn Print = wrapper.downcast_ref().unwrap();
+  assert_eq!(print.print(), 1);
+  let wrapper = TraitcastableAnyInfraExt::<dyn Print>::downcast(wrapper)
+    .err()
+    .unwrap();
+  let wrapper: Rc<dyn TraitcastableAny> = Rc::from(wrapper);
+  let wrapper = TraitcastableUniqueExt::<dyn Print>::try_downcast_unique(wrapper)
+    .err()
+    .unwrap();
+  assert!(TraitcastableAnyInfraExt::<dyn Print>::downcast(wrapper).is_err());
+
+  let stack: StackBox<dyn TraitcastableAny, 32> = StackBox::new(Wrapper {
+    inner: Inner(3),
+    extra: 4,
+  });
+  assert!(TraitcastableAnyInfraExt::<dyn Print>::downcast(stack).is_err());
+
+  // Cloning must not clone only the delegate.
+  let wrapper: Box<dyn TraitcastableAny> = Box::new(CloneWrapper {
+    inner: Inner(5),
+    extra: 6,
+  });
+  assert!(!wrapper.is_clonable());
+  let wrapper: Box<dyn TraitcastableAny> = Box::new(Wrapper {
+    inner: Inner(7),
+    extra: 8,
+  });
+  let cloned = wrapper.try_clone_box().unwrap();
+  let cloned: &Wrapper = cloned.downcast_ref().unwrap();
+  assert_eq!((cloned.inner.0, cloned.extra), (7, 8));
+}
+
+#[test]
+fn test_scoped_and_delegated_targets() {
+  use crate::TraitcastableAnyInfraExt;
+
+  mod auth {
+    pub struct Key(());
+    pub const fn key() -> Key {
+      Key(())
+    }
+  }
+  trait Admin {
+    fn level(&self) -> i32;
+  }
+  struct Inner(i32);
+  impl Admin for Inner {
+    fn level(&self) -> i32 {
+      self.0
+    }
+  }
+  #[repr(C)]
+  struct Outer {
+    level: i32,
+    inner: Inner,
+  }
+  impl Admin for Outer {
+    fn level(&self) -> i32 {
+      self.level
+    }
+  }
+  enum Either {
+    Inner(Inner),
+    Level(i32),
+  }
+  impl Admin for Either {
+    fn level(&self) -> i32 {
+      match self {
+        Self::Inner(inner) => inner.0 * 10,
+        Self::Level(level) => *level,
+      }
+    }
+  }
+  make_trait_castable_decl! {
+    Inner => (Admin),
+    Outer => () in auth::Key => (Admin) delegate inner,
+    Either => () in auth::Key => (Admin) delegate { Either::Inner(inner) => inner },
+  }
+
+  // Casts without the key resolve to the delegate, casts with the key to the value itself.
+  let outer: Box<dyn TraitcastableAny> = Box::new(Outer {
+    level: 42,
+    inner: Inner(7),
+  });
+  let admin: &dyn Admin = outer.downcast_ref().unwrap();
+  assert_eq!(admin.level(), 7);
+  let admin: &dyn Admin = outer.downcast_ref_scoped(&auth::key()).unwrap();
+  assert_eq!(admin.level(), 42);
+  let mut outer = TraitcastableAnyInfraExt::<dyn Admin>::downcast(outer)
+    .err()
+    .unwrap();
+  let admin: &mut dyn Admin = outer.downcast_mut().unwrap();
+  assert_eq!(admin.level(), 7);
+  let admin: &mut dyn Admin = outer.downcast_mut_scoped(&auth::key()).unwrap();
+  assert_eq!(admin.level(), 42);
+
+  let either: Box<dyn TraitcastableAny> = Box::new(Either::Inner(Inner(3)));
+  let admin: &dyn Admin = either.downcast_ref().unwrap();
+  assert_eq!(admin.level(), 3);
+  let admin: &dyn Admin = either.downcast_ref_scoped(&auth::key()).unwrap();
+  assert_eq!(admin.level(), 30);
+  assert!(TraitcastableAnyInfraExt::<dyn Admin>::downcast(either).is_err());
+  let level: Box<dyn TraitcastableAny> = Box::new(Either::Level(5));
+  let admin: Option<&dyn Admin> = level.downcast_ref();
+  assert!(admin.is_none());
+  let admin: &dyn Admin = level.downcast_ref_scoped(&auth::key()).unwrap();
+  assert_eq!(admin.level(), 5);
+}
+
+#[test]
+fn test_enum_delegate() {
+  use crate::TraitcastableAnyInfraExt;
+
+  trait Area {
+    fn area(&self) -> i32;
+  }
+  struct Circle(i32);
+  impl Area for Circle {
+    fn area(&self) -> i32 {
+      3 * self.0 * self.0
+    }
+  }
+  struct Square(i32);
+  impl Area for Square {
+    fn area(&self) -> i32 {
+      self.0 * self.0
+    }
+  }
+  enum Shape {
+    Circle(Circle),
+    Square { side: Square },
+    Printable(Source),
+    Empty,
+  }
+  make_trait_castable_decl! {
+    Circle => (Area),
+    Square => (Area),
+    Shape => () delegate {
+      Self::Circle(payload) => payload,
+      Self::Square { side: payload } => payload,
+      Self::Printable(payload) => payload,
+    },
+    One => () delegate { Self::A(payload) => payload },
+  }
+  enum One {
+    A(Circle),
+  }
+
+  let mut circle: Box<dyn TraitcastableAny> = Box::new(Shape::Circle(Circle(2)));
+  let area: &dyn Area = circle.downcast_ref().unwrap();
+  assert_eq!(area.area(), 12);
+  assert!(TraitcastableAnyInfra::<dyn Area>::can_be(circle.as_ref()));
+  assert!(!TraitcastableAnyInfra::<dyn Print>::can_be(circle.as_ref()));
+  let area: &mut dyn Area = circle.downcast_mut().unwrap();
+  assert_eq!(area.area(), 12);
+  // Concrete types are cast through `Any`, so only the enum itself is found.
+  let payload: Option<&Circle> = circle.downcast_ref();
+  assert!(payload.is_none());
+  let shape: &Shape = circle.downcast_ref().unwrap();
+  assert!(matches!(shape, Shape::Circle(Circle(2))));
+
+  let square: Box<dyn TraitcastableAny> = Box::new(Shape::Square { side: Square(4) });
+  let area: &dyn Area = square.downcast_ref().unwrap();
+  assert_eq!(area.area(), 16);
+  // Delegated targets never resolve to the enum itself, so owned casts fail.
+  assert!(TraitcastableAnyInfraExt::<dyn Area>::downcast(square).is_err());
+
+  let printable: Box<dyn TraitcastableAny> = Box::new(Shape::Printable(Source(5)));
+  let print: &dyn Print = printable.downcast_ref().unwrap();
+  assert_eq!(print.print(), 5);
+  assert!(!TraitcastableAnyInfra::<dyn Area>::can_be(
+    printable.as_ref()
+  ));
+
+  let empty: Box<dyn TraitcastableAny> = Box::new(Shape::Empty);
+  assert!(!TraitcastableAnyInfra::<dyn Area>::can_be(empty.as_ref()));
+  let shape: Option<&Shape> = empty.downcast_ref();
+  assert!(shape.is_some());
+
+  // The payload of a single variant enum is stored at the address of the enum.
+  let one: Box<dyn TraitcastableAny> = Box::new(One::A(Circle(1)));
+  let area: &dyn Area = one.downcast_ref().unwrap();
+  assert_eq!(area.area(), 3);
+  let one = TraitcastableAnyInfraExt::<dyn Area>::downcast(one)
+    .err()
+    .unwrap();
+  let one: &One = one.downcast_ref().unwrap();
+  let One::A(circle) = one;
+  assert_eq!(circle.0, 1);
+}
+
+#[test]
+fn test_guarded_targets() {
+  use crate::{
+    CastError, DispatchTable, StableId, StableIdRegistry, TraitMap, TraitcastableAnyInfraExt,
+  };
+  use core::any::TypeId;
+
+  trait Editable {
+    fn insert(&mut self, text: &'static str);
+  }
+  struct Document {
+    locked: bool,
+    text: &'static str,
+  }
+  impl Document {
+    const fn is_unlocked(&self) -> bool {
+      !self.locked
+    }
+  }
+  impl Editable for Document {
+    fn insert(&mut self, text: &'static str) {
+      self.text = text;
+    }
+  }
+  impl Print for Document {
+    fn print(&self) -> i32 {
+      self.text.len().try_into().unwrap()
+    }
+  }
+  make_trait_castable_decl! {
+    Document => (Print) if Self::is_unlocked => (Editable),
+  }
+
+  let mut document: Box<dyn TraitcastableAny> = Box::new(Document {
+    locked: true,
+    text: "",
+  });
+  assert!(!TraitcastableAnyInfra::<dyn Editable>::can_be(
+    document.as_ref()
+  ));
+  assert!(TraitcastableAnyInfra::<dyn Print>::can_be(
+    document.as_ref()
+  ));
+  let editable: Option<&mut dyn Editable> = document.downcast_mut();
+  assert!(editable.is_none());
+  assert!(matches!(
+    document.try_downcast_ref::<dyn Editable>(),
+    Err(CastError::Rejected { .. })
+  ));
+  assert!(matches!(
+    document.try_downcast_mut::<dyn Send>(),
+    Err(CastError::NotCastable { .. })
+  ));
+  assert!(document.cast_erased(TypeId::of::<dyn Editable>()).is_none());
+  // Owned casts honor the guard too.
+  let mut document = TraitcastableAnyInfraExt::<dyn Editable>::downcast(document)
+    .err()
+    .unwrap();
+
+  let concrete: &mut Document = document.downcast_mut().unwrap();
+  concrete.locked = false;
+  document
+    .try_downcast_mut::<dyn Editable>()
+    .unwrap()
+    .insert("hello");
+  let print: &dyn Print = document.downcast_ref().unwrap();
+  assert_eq!(print.print(), 5);
+  let editable: Box<dyn Editable> = document.downcast().ok().unwrap();
+  drop(editable);
+
+  // The `TraitMap` and the `DispatchTable` check the guard on lookup.
+  let mut map = TraitMap::new();
+  let key = map.insert(Box::new(Document {
+    locked: true,
+    text: "",
+  }));
+  assert_eq!(map.count::<dyn Editable>(), 0);
+  assert_eq!(map.count::<dyn Print>(), 1);
+  let mut table = DispatchTable::new();
+  table
+    .register::<dyn Editable, dyn Print>(|_, _| 1)
+    .register::<dyn Print, dyn Print>(|_, _| 2);
+  let locked = map.object(key).unwrap();
+  assert_eq!(table.dispatch(locked, locked), Some(2));
+  map.cast_mut::<Document>(key).unwrap().locked = false;
+  assert_eq!(map.count::<dyn Editable>(), 1);
+  let unlocked = map.object(key).unwrap();
+  assert_eq!(table.dispatch(unlocked, unlocked), Some(1));
+
+  // So does the `StableIdRegistry`.
+  let mut registry = StableIdRegistry::new();
+  registry
+    .register_target_with_id::<dyn Editable>(StableId::from_raw(1))
+    .unwrap();
+  registry
+    .register_target_with_id::<dyn Print>(StableId::from_raw(2))
+    .unwrap();
+  assert_eq!(registry.target_ids_of(unlocked).count(), 2);
+  map.cast_mut::<Document>(key).unwrap().locked = true;
+  let locked = map.object(key).unwrap();
+  assert!(registry.target_ids_of(locked).eq([StableId::from_raw(2)]));
+}
+
+#[cfg(feature = "std_impls")]
+#[test]
+fn test_std_impls() {
+  use crate::{ClonableBox, DynKey, TraitcastableCloneExt};
+  use alloc::{format, string::String, vec, vec::Vec};
+  use core::{
+    error::Error,
+    fmt::{Debug, Display},
+  };
+
+  let number: Box<dyn TraitcastableAny> = Box::new(42_i32);
+  let display: &dyn Display = number.downcast_ref().unwrap();
+  assert_eq!(format!("{display}"), "42");
+  assert_eq!(
+    DynKey(Box::new(42_i32) as Box<dyn TraitcastableAny>),
+    DynKey(number)
+  );
+
+  let text: Box<dyn TraitcastableAny> = Box::new(String::from("text"));
+  let cloned = text.try_clone_box().unwrap();
+  let display: &dyn Display = cloned.downcast_ref().unwrap();
+  assert_eq!(format!("{display}"), "text");
+
+  let list: Box<dyn TraitcastableAny> = Box::new(vec![1_u8, 2]);
+  let list = ClonableBox::try_new(list).ok().unwrap();
+  let list = list.clone().into_inner();
+  let debug: &dyn Debug = list.downcast_ref().unwrap();
+  assert_eq!(format!("{debug:?}"), "[1, 2]");
+  assert!(TraitcastableAnyInfra::<dyn Display>::downcast_ref(list.as_ref()).is_none());
+  let concrete: &Vec<u8> = list.downcast_ref().unwrap();
+  assert_eq!(concrete.len(), 2);
+
+  // With `min_specialization` `&'static str` has the blanket implementation without targets.
+  let text: Box<dyn TraitcastableAny> = Box::new("static");
+  let display: Option<&dyn Display> = text.downcast_ref();
+  assert_eq!(display.is_some(), cfg!(not(feature = "min_specialization")));
+
+  let error: Box<dyn TraitcastableAny> = Box::new("x".parse::<u8>().unwrap_err());
+  let error: &dyn Error = error.downcast_ref().unwrap();
+  assert_eq!(format!("{error}"), "invalid digit found in string");
+}
@@ -2,12 +2,14 @@ use core::{
   any::{Any, TypeId, type_name},
   fmt::{self, Debug, Formatter},
   ptr,
-  ptr::DynMetadata,
+  ptr::{DynMetadata, Pointee},
 };
 
 #[cfg(feature = "alloc")]
 use alloc::{boxed::Box, rc::Rc, sync::Arc};
 
+use crate::{Request, StableId, StableTypeId, request_ref};
+
 /// This trait must be implemented on every concrete type for every trait that `TraitcastableAny`
 /// should be able to downcast to.
 ///
@@ -20,9 +22,16 @@ pub trait TraitcastableTo<Target: 'static + ?Sized>: TraitcastableAny {
 /// A struct representing the transformation from `dyn TraitcastableAny` to another `dyn Trait`.
 ///
 /// This should generally not be manually used, but generated by the `make_trait_castable` attribute macro.
+#[derive(Clone, Copy)]
 pub struct TraitcastTarget {
   target_type_id: TypeId,
   target_type_name: &'static str,
+  /// Only set for targets that opted into plugin-safe casting.
+  target_stable_id: Option<StableId>,
+  /// Only set for scoped targets, which can only be cast to by holders of the key.
+  key_type_id: Option<TypeId>,
+  /// The byte offset of the field the target casts to. Only set for field targets.
+  projection: Option<usize>,
   /// Must point to the `DynMetadata<T>` (where T is the type in `TypeId`)
   metadata: *const (),
 }
@@ -34,14 +43,139 @@ impl TraitcastTarget {
     Self {
       target_type_id: TypeId::of::<Target>(),
       target_type_name: type_name::<Target>(),
+      target_stable_id: None,
+      key_type_id: None,
+      projection: None,
       metadata: ptr::from_ref::<DynMetadata<Target>>(&Src::METADATA).cast::<()>(),
     }
   }
+  /// Creates a new `TraitcastTarget` from a `TraitcastableTo` implementation, that can also be found by the `StableId` of `Target`.
+  ///
+  /// This allows casting objects created by separately compiled artifacts (like plugins). See `cast_stable`.
+  #[must_use]
+  pub const fn from_stable<Src: TraitcastableTo<Target>, Target: StableTypeId + ?Sized>() -> Self {
+    let mut target = Self::from::<Src, Target>();
+    target.target_stable_id = Some(Target::STABLE_ID);
+    target
+  }
+  /// Creates a new scoped `TraitcastTarget` from a `TraitcastableTo` implementation, that can only be cast to with a reference to a `Key`.
+  ///
+  /// Scoped targets are returned by `TraitcastableAny::traitcast_scoped_targets`. See `downcast_ref_scoped`.
+  #[must_use]
+  pub const fn from_scoped<
+    Src: TraitcastableTo<Target>,
+    Target: 'static + ?Sized,
+    Key: 'static + ?Sized,
+  >() -> Self {
+    let mut target = Self::from::<Src, Target>();
+    target.key_type_id = Some(TypeId::of::<Key>());
+    target
+  }
   /// Returns the `TypeId` of the type to which can be cast with this instance.
   #[must_use]
   pub const fn target_type_id(&self) -> TypeId {
     self.target_type_id
   }
+  /// Returns the `StableId` of the type to which can be cast with this instance, if it was created with `from_stable`.
+  #[must_use]
+  pub const fn target_stable_id(&self) -> Option<StableId> {
+    self.target_stable_id
+  }
+  /// Creates a new `TraitcastTarget` that casts to the field at the byte offset instead of the value itself.
+  ///
+  /// This should generally not be manually used, but generated by the `make_trait_castable` attribute macro
+  ///  for the targets of fields marked with `#[traitcast(expose(...))]`.
+  ///
+  /// # Safety
+  /// The metadata must be the one of the type of the field at the offset for `Target`.
+  #[must_use]
+  pub const unsafe fn from_field<Target: 'static + ?Sized>(
+    offset: usize,
+    metadata: &'static DynMetadata<Target>,
+  ) -> Self {
+    Self {
+      target_type_id: TypeId::of::<Target>(),
+      target_type_name: type_name::<Target>(),
+      target_stable_id: None,
+      key_type_id: None,
+      projection: Some(offset),
+      metadata: ptr::from_ref::<DynMetadata<Target>>(metadata).cast::<()>(),
+    }
+  }
+  /// Returns the `TypeId` of the key required to cast with this instance, if it is a scoped target.
+  #[must_use]
+  pub const fn key_type_id(&self) -> Option<TypeId> {
+    self.key_type_id
+  }
+  /// Returns the byte offset of the field to which is cast with this instance, if it is a field target.
+  ///
+  /// Owning pointers (like `Box`) can't be cast to field targets.
+  #[must_use]
+  pub const fn projection(&self) -> Option<usize> {
+    self.projection
+  }
+  /// Returns the byte offset of the value to which is cast with this instance from the address of the object.
+  pub(crate) const fn offset(&self) -> usize {
+    match self.projection {
+      Some(offset) => offset,
+      None => 0,
+    }
+  }
+  /// Returns the `type_name` of the type to which can be cast with this instance. Something like `dyn my_crate::Renderer`.
+  #[must_use]
+  pub const fn target_type_name(&self) -> &'static str {
+    self.target_type_name
+  }
+  /// Returns the name of the type to which can be cast with this instance, without the `dyn` and the module path.
+  ///
+  /// Only the path of the trait itself is stripped, generic arguments are kept as they are.
+  /// For `dyn my_crate::Dog<my_crate::Bone>` this returns `Dog<my_crate::Bone>`.
+  #[must_use]
+  pub fn target_short_name(&self) -> &'static str {
+    let name = self.target_path_name();
+    let path_end = name.find('<').unwrap_or(name.len());
+    name[..path_end]
+      .rfind("::")
+      .map_or(name, |segment_start| &name[segment_start + 2..])
+  }
+  /// Returns true if `name` is the `target_type_name`, the `target_type_name` without `dyn ` or the `target_short_name`.
+  #[must_use]
+  pub fn matches_name(&self, name: &str) -> bool {
+    name == self.target_type_name
+      || name == self.target_path_name()
+      || name == self.target_short_name()
+  }
+  pub(crate) fn target_path_name(&self) -> &'static str {
+    self
+      .target_type_name
+      .strip_prefix("dyn ")
+      .unwrap_or(self.target_type_name)
+  }
+  /// Returns the metadata of this instance if `Target` has the `StableId` of the type to which can be cast with it.
+  ///
+  /// # Safety
+  /// The type with the `StableId` must have the same layout and vtable layout as `Target`.
+  /// This holds if the artifact that created this instance was compiled against the same interface with the same compiler.
+  #[must_use]
+  pub unsafe fn metadata_stable<Target: StableTypeId + ?Sized>(
+    &self,
+  ) -> Option<<Target as Pointee>::Metadata> {
+    (self.target_stable_id == Some(Target::STABLE_ID)).then(|| {
+      // SAFETY:
+      // The invariant of Traitcast target guarantees that the metadata points to an instance of the metadata of the type with the `StableId`.
+      // The caller guarantees that it is compatible with `Target`.
+      unsafe { *(self.metadata.cast::<<Target as Pointee>::Metadata>()) }
+    })
+  }
+  /// Returns the metadata of this instance if `Target` is the type to which can be cast with it.
+  #[must_use]
+  pub fn metadata<Target: ?Sized + 'static>(&self) -> Option<<Target as Pointee>::Metadata> {
+    (self.target_type_id == TypeId::of::<Target>()).then(|| {
+      // SAFETY:
+      // The invariant of Traitcast target guarantees that the metadata points to an instance of `<Target as ::core::ptr::Pointee>::Metadata`.
+      unsafe { *(self.metadata.cast::<<Target as Pointee>::Metadata>()) }
+    })
+  }
 }
 
 /// A trait marking a type as being potentially able to traitcast from `dyn TraitcastableAny` to another `dyn Trait`.
@@ -52,6 +186,10 @@ impl TraitcastTarget {
 /// # Safety
 /// The function `traitcast_targets` must only produce valid `TraitcastTarget` (That use the metadata associated the the correct source struct).
 /// The function `find_traitcast_target` must not return `Some` unless contained value has the correct target `TypeId`.
+/// The function `traitcast_scoped_targets` must only produce valid `TraitcastTarget` for self, scoped targets always resolve to self.
+/// The functions `traitcast_address` and `traitcast_address_mut` must only return `None` if the metadata of the target
+///  found by `find_traitcast_target` for the `TypeId` is valid for self. Otherwise they must return the address of a value,
+///  that lives as long as the borrow of self and for which the metadata of that target is valid.
 pub unsafe trait TraitcastableAny: Any {
   /// This function returns a list of all the `TraitcastTarget`'s to which a trait object can be cast, this is then used by the implementations of `TraitcastableAnyInfra` to accomplish the traitcast.
   /// The function is used to generate debug output for `TraitcastableAny`.
@@ -76,10 +214,55 @@ pub unsafe trait TraitcastableAny: Any {
       .find(|possible| possible.target_type_id == target)
   }
 
+  /// Returns the scoped `TraitcastTarget`s, which can only be cast to with a reference to their key.
+  ///
+  /// They are not part of `traitcast_targets`, so they are neither found by the regular casts nor listed by `Debug`.
+  /// Scoped targets always resolve to the value itself, `traitcast_address` is not consulted for them.
+  /// See `downcast_ref_scoped`.
+  ///
+  /// This should generally not be manually implemented, but generated by the `make_trait_castable` attribute macro.
+  fn traitcast_scoped_targets(&self) -> &[TraitcastTarget] {
+    &[]
+  }
+
+  /// Supplies values, which are not self reinterpreted as a cast target, to a `Request`.
+  ///
+  /// `downcast_ref` falls back to this when no target matches. This allows exposing traits implemented
+  ///  by a field, by a lazily created helper or by a value chosen at runtime. See `request_ref`.
+  /// Owning pointers (like `Box`) can't be cast to the supplied values.
+  fn provide<'a>(&'a self, request: &mut Request<'a>) {
+    let _ = request;
+  }
+
   /// Returns the `TypeId` of the concrete type.
   fn type_id(&self) -> TypeId {
     Any::type_id(self)
   }
+
+  /// Returns the data pointer of the value a cast to the target with the `TypeId` resolves to, if it is not self.
+  ///
+  /// Defaults to `None`, casts resolve to self. Objects forwarding casts to values they own, like `Composite`
+  ///  or delegating structs, return the address of that value instead.
+  /// Casts of owning pointers (like `Box`) only succeed for targets resolving to self.
+  fn traitcast_address(&self, target: TypeId) -> Option<*const ()> {
+    let _ = target;
+    None
+  }
+
+  /// Mutable variant of `traitcast_address`.
+  fn traitcast_address_mut(&mut self, target: TypeId) -> Option<*mut ()> {
+    let _ = target;
+    None
+  }
+
+  /// Returns false if the guard of the target with the `TypeId` currently rejects casts to it.
+  ///
+  /// Guarded targets are listed as `Trait if guard` in the `make_trait_castable` attribute macro.
+  /// Every cast calls this, so whether a cast succeeds can depend on the state of the object. Defaults to true.
+  fn traitcast_allowed(&self, target: TypeId) -> bool {
+    let _ = target;
+    true
+  }
 }
 
 /// Mimics the API of `Any` but additionally allows downcasts to select trait objects.
@@ -90,6 +273,9 @@ pub trait TraitcastableAnyInfra<Target: ?Sized>: 'static {
   fn is(&self) -> bool;
 
   /// Returns true if Self can be converted to a `Target`.
+  ///
+  /// This includes values supplied by `TraitcastableAny::provide`, so `downcast_ref` succeeds if this returns true.
+  /// `downcast_mut` and the owned casts never fall back to `provide` and may still fail.
   fn can_be(&self) -> bool;
 
   /// Returns some reference to the inner value if it is downcastable to `Target`, or `None` if it isn’t.
@@ -97,6 +283,9 @@ pub trait TraitcastableAnyInfra<Target: ?Sized>: 'static {
   /// If `Target` is Sized this is forwarded to `Any::downcast_ref`,
   /// otherwise `TraitcastableAny::traitcast_targets` is used to determine if a traitcast is possible.
   ///
+  /// Targets whose guard rejects the cast (see `TraitcastableAny::traitcast_allowed`) don't match.
+  /// If no target matches, the value is requested from `TraitcastableAny::provide`.
+  ///
   /// Returns `None` if the concrete type of self is not `Target` and a traitcast is not possible.
   fn downcast_ref(&self) -> Option<&Target>;
 
@@ -111,6 +300,7 @@ pub trait TraitcastableAnyInfra<Target: ?Sized>: 'static {
   ///
   /// If `Target` is Sized this is forwarded to `Any::downcast_ref`,
   /// otherwise `TraitcastableAny::traitcast_targets` is used to determine if a traitcast is possible.
+  /// Unlike `downcast_ref` this never falls back to `TraitcastableAny::provide`.
   ///
   /// Returns `None` if the concrete type of self is not `Target` and a traitcast is not possible.
   fn downcast_mut(&mut self) -> Option<&mut Target>;
@@ -121,15 +311,24 @@ pub trait TraitcastableAnyInfra<Target: ?Sized>: 'static {
   #[cfg(feature = "downcast_unchecked")]
   #[doc(cfg(feature = "downcast_unchecked"))]
   unsafe fn downcast_mut_unchecked(&mut self) -> &mut Target;
+
+  /// Same as `downcast_ref`, except that it only succeeds if the result is self with different pointer metadata.
+  ///
+  /// Owning pointers (like `Box`) use this to rebuild themselves as a pointer to `Target`.
+  /// Only targets for which `TraitcastableAny::traitcast_address` returns `None` resolve to self.
+  /// Casts resolving to values owned by self, like the components of a `Composite`, delegates, fields
+  ///  or values supplied by `provide`, fail.
+  fn downcast_ref_in_place(&self) -> Option<&Target>;
+
+  /// Mutable variant of `downcast_ref_in_place`.
+  fn downcast_mut_in_place(&mut self) -> Option<&mut Target>;
 }
 
 // TODO: Allocator api support.
 
-/// Extension Trait to implement over Smart Pointer Types (`Box`, `Rc`, `Arc`).
+/// Extension Trait to implement over Smart Pointer Types (`Box`, `Rc`, `Arc`, `StackBox`).
 ///
 /// Tries to mimic the API of `Any` but additionally allows downcasts to select trait objects.
-#[cfg(feature = "alloc")]
-#[doc(cfg(feature = "alloc"))]
 pub trait TraitcastableAnyInfraExt<Target: ?Sized + 'static>: Sized {
   /// The type that will be returned on a successful cast. Something like `Box<Target>`.
   type Output;
@@ -137,6 +336,8 @@ pub trait TraitcastableAnyInfraExt<Target: ?Sized + 'static>: Sized {
   /// Same as `downcast_ref` and `downcast_mut`, except that it downcasts a `Box` in place.
   ///
   /// Returns `None` if the concrete type of self is not `Target` and a traitcast is not possible.
+  /// Casts to values that are not the object itself, like the components of a `Composite`, are impossible.
+  /// See `TraitcastableAnyInfra::downcast_ref_in_place`.
   ///
   /// # Errors
   /// In case a cast is impossible the original input is returned as the error type.
@@ -166,6 +367,24 @@ unsafe impl<T: 'static> TraitcastableAny for T {
       .iter()
       .find(|possible| possible.target_type_id == target)
   }
+  default fn traitcast_scoped_targets(&self) -> &[TraitcastTarget] {
+    &[]
+  }
+  default fn provide<'a>(&'a self, request: &mut Request<'a>) {
+    let _ = request;
+  }
+  default fn traitcast_address(&self, target: TypeId) -> Option<*const ()> {
+    let _ = target;
+    None
+  }
+  default fn traitcast_address_mut(&mut self, target: TypeId) -> Option<*mut ()> {
+    let _ = target;
+    None
+  }
+  default fn traitcast_allowed(&self, target: TypeId) -> bool {
+    let _ = target;
+    true
+  }
 }
 impl Debug for dyn TraitcastableAny {
   fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
@@ -179,6 +398,48 @@ impl Debug for dyn TraitcastableAny {
     write!(f, "}}")
   }
 }
+
+/// Upcasts to `dyn TraitcastableAny`, dropping any marker traits.
+///
+/// Allows writing code that is generic over `dyn TraitcastableAny` and its `Send`/`Sync` variants.
+pub trait AsTraitcastableAny {
+  /// Returns `self` as a `&dyn TraitcastableAny`.
+  fn as_traitcastable_any(&self) -> &dyn TraitcastableAny;
+}
+impl<T: TraitcastableAny> AsTraitcastableAny for T {
+  fn as_traitcastable_any(&self) -> &dyn TraitcastableAny {
+    self
+  }
+}
+impl AsTraitcastableAny for dyn TraitcastableAny {
+  fn as_traitcastable_any(&self) -> &dyn TraitcastableAny {
+    self
+  }
+}
+impl AsTraitcastableAny for dyn TraitcastableAny + Send {
+  fn as_traitcastable_any(&self) -> &dyn TraitcastableAny {
+    self
+  }
+}
+impl AsTraitcastableAny for dyn TraitcastableAny + Send + Sync {
+  fn as_traitcastable_any(&self) -> &dyn TraitcastableAny {
+    self
+  }
+}
+impl dyn TraitcastableAny {
+  /// Returns the data pointer a cast to the target with the `TypeId` resolves to.
+  pub(crate) fn target_address(&self, target: TypeId) -> *const () {
+    TraitcastableAny::traitcast_address(self, target)
+      .unwrap_or_else(|| ptr::from_ref(self).cast::<()>())
+  }
+
+  /// Mutable variant of `target_address`.
+  pub(crate) fn target_address_mut(&mut self, target: TypeId) -> *mut () {
+    TraitcastableAny::traitcast_address_mut(self, target)
+      .unwrap_or_else(|| ptr::from_mut(self).cast::<()>())
+  }
+}
+
 macro_rules! implement_with_markers {
   ($($(+)? $traits:ident)*) => {
     impl<Target: ?Sized + 'static + $($traits +)*> TraitcastableAnyInfra<Target> for dyn TraitcastableAny $(+ $traits)* {
@@ -187,24 +448,24 @@ macro_rules! implement_with_markers {
       }
       default fn can_be(&self) -> bool {
         let found_target = self.find_traitcast_target(TypeId::of::<Target>());
-        found_target.is_some()
+        (found_target.is_some() && Self::traitcast_allowed(self, TypeId::of::<Target>())) || request_ref::<Target>(self).is_some()
       }
 
       default fn downcast_ref(&self) -> Option<&Target> {
         // SAFETY:
         // The invariant of Traitcast target guarantees that the metadata points to an instance of `<Target as ::core::ptr::Pointee>::Metadata`.
-        let metadata = Self::find_traitcast_target(self, TypeId::of::<Target>()).map(|target| unsafe {*(target.metadata.cast::<<Target as ::core::ptr::Pointee>::Metadata>())});
+        let found = Self::find_traitcast_target(self, TypeId::of::<Target>()).filter(|_| Self::traitcast_allowed(self, TypeId::of::<Target>())).map(|target| (unsafe {*(target.metadata.cast::<<Target as ::core::ptr::Pointee>::Metadata>())}, target.offset()));
 
-        let raw_ptr = core::ptr::from_ref::<Self>(self).to_raw_parts().0;
+        let raw_ptr = <dyn TraitcastableAny>::target_address(self, TypeId::of::<Target>());
 
-        metadata.map(|metadata| {
-          let ret_ptr: *const Target = ptr::from_raw_parts(raw_ptr, metadata);
+        found.map(|(metadata, offset)| {
+          let ret_ptr: *const Target = ptr::from_raw_parts(raw_ptr.wrapping_byte_add(offset), metadata);
 
           // SAFETY:
           // we turned this into a raw pointer before and changed the metadata to that of a dyn Trait
           //  where the Trait must be implemented, so this must be safe!
           unsafe {&*ret_ptr}
-        })
+        }).or_else(|| request_ref(self))
       }
       #[cfg(feature = "downcast_unchecked")]
       default unsafe fn downcast_ref_unchecked(&self) -> &Target {
@@ -215,12 +476,12 @@ macro_rules! implement_with_markers {
       default fn downcast_mut(&mut self) -> Option<&mut Target> {
         // SAFETY:
         // The invariant of Traitcast target guarantees that the metadata points to an instance of `<Target as ::core::ptr::Pointee>::Metadata`.
-        let metadata = Self::find_traitcast_target(self, TypeId::of::<Target>()).map(|target| unsafe {*(target.metadata.cast::<<Target as ::core::ptr::Pointee>::Metadata>())});
+        let found = Self::find_traitcast_target(self, TypeId::of::<Target>()).filter(|_| Self::traitcast_allowed(self, TypeId::of::<Target>())).map(|target| (unsafe {*(target.metadata.cast::<<Target as ::core::ptr::Pointee>::Metadata>())}, target.offset()));
 
-        let raw_ptr = core::ptr::from_mut::<Self>(self).to_raw_parts().0;
+        let raw_ptr = <dyn TraitcastableAny>::target_address_mut(self, TypeId::of::<Target>());
 
-        metadata.map(|metadata| {
-          let ret_ptr: *mut Target = ptr::from_raw_parts_mut(raw_ptr, metadata);
+        found.map(|(metadata, offset)| {
+          let ret_ptr: *mut Target = ptr::from_raw_parts_mut(raw_ptr.wrapping_byte_add(offset), metadata);
 
           // SAFETY:
           // we turned this into a raw pointer before and changed the metadata to that of a dyn Trait
@@ -234,6 +495,36 @@ macro_rules! implement_with_markers {
         // SAFETY: The caller must ensure that the cast is valid.
         unsafe { self.downcast_mut().unwrap_unchecked() }
       }
+
+      default fn downcast_ref_in_place(&self) -> Option<&Target> {
+        // Only own targets resolve to self. Delegated targets and the targets of components resolve to a value owned by self,
+        //  even if it is stored at the address of self.
+        if self.traitcast_address(TypeId::of::<Target>()).is_some() {
+          return None;
+        }
+        let raw_ptr = core::ptr::from_ref::<Self>(self).to_raw_parts().0;
+        // SAFETY:
+        // The invariant of Traitcast target guarantees that the metadata points to an instance of `<Target as ::core::ptr::Pointee>::Metadata`.
+        let metadata = Self::find_traitcast_target(self, TypeId::of::<Target>()).filter(|target| target.projection.is_none() && Self::traitcast_allowed(self, TypeId::of::<Target>())).map(|target| unsafe {*(target.metadata.cast::<<Target as ::core::ptr::Pointee>::Metadata>())})?;
+        // SAFETY:
+        // The target resolves to self and is not a field target, so only the metadata changed.
+        Some(unsafe {&*ptr::from_raw_parts::<Target>(raw_ptr, metadata)})
+      }
+
+      default fn downcast_mut_in_place(&mut self) -> Option<&mut Target> {
+        // Only own targets resolve to self. Delegated targets and the targets of components resolve to a value owned by self,
+        //  even if it is stored at the address of self.
+        if Self::traitcast_address_mut(self, TypeId::of::<Target>()).is_some() {
+          return None;
+        }
+        let raw_ptr = core::ptr::from_mut::<Self>(self).to_raw_parts().0;
+        // SAFETY:
+        // The invariant of Traitcast target guarantees that the metadata points to an instance of `<Target as ::core::ptr::Pointee>::Metadata`.
+        let metadata = Self::find_traitcast_target(self, TypeId::of::<Target>()).filter(|target| target.projection.is_none() && Self::traitcast_allowed(self, TypeId::of::<Target>())).map(|target| unsafe {*(target.metadata.cast::<<Target as ::core::ptr::Pointee>::Metadata>())})?;
+        // SAFETY:
+        // The target resolves to self and is not a field target, so only the metadata changed.
+        Some(unsafe {&mut *ptr::from_raw_parts_mut::<Target>(raw_ptr, metadata)})
+      }
     }
     impl<Target: Sized + 'static + $($traits +)*> TraitcastableAnyInfra<Target> for dyn TraitcastableAny $(+ $traits)* {
       fn is(&self) -> bool {
@@ -254,6 +545,12 @@ macro_rules! implement_with_markers {
       fn downcast_mut(&mut self) -> Option<&mut Target> {
         <dyn Any>::downcast_mut::<Target>(self)
       }
+      fn downcast_ref_in_place(&self) -> Option<&Target> {
+        <dyn Any>::downcast_ref::<Target>(self)
+      }
+      fn downcast_mut_in_place(&mut self) -> Option<&mut Target> {
+        <dyn Any>::downcast_mut::<Target>(self)
+      }
       #[cfg(feature = "downcast_unchecked")]
       unsafe fn downcast_mut_unchecked(&mut self) -> &mut Target {
         // SAFETY: We are just forwarding the call to the `Any` trait.
@@ -263,176 +560,112 @@ macro_rules! implement_with_markers {
   };
 }
 
-#[cfg(feature = "alloc")]
-impl<Src: TraitcastableAnyInfra<Target> + ?Sized, Target: ?Sized + 'static>
-  TraitcastableAnyInfraExt<Target> for Box<Src>
-{
-  type Output = Box<Target>;
+/// An owning pointer that can be decomposed into a raw pointer and rebuilt with different pointer metadata.
+///
+/// Implementing this trait provides `TraitcastableAnyInfraExt::downcast` for the pointer.
+/// It is implemented for `Box`, `Rc` and `Arc` and allows third party pointer types to opt in.
+///
+/// Pointers that only support pointees with certain marker traits (like `Send + Sync`),
+/// can express this by adding the bounds to their implementation of this trait.
+/// Casts are then only possible to targets for which `Rebind<Target>` implements this trait.
+///
+/// # Safety
+/// `from_raw` must accept any pointer returned by `into_raw` and reconstruct the original pointer.
+/// `Rebind<U>` must be the same kind of pointer to `U`. Its `from_raw` must also accept any pointer returned by `into_raw`
+///  whose metadata was replaced with valid metadata for `U` of the same value.
+pub unsafe trait TraitcastablePointer: Sized {
+  /// The type the pointer points to. Something like `dyn TraitcastableAny`.
+  type Pointee: ?Sized;
 
-  default fn downcast(self) -> Result<Self::Output, Self> {
-    let raw = Self::into_raw(self);
-    // SAFETY:
-    // We can cast the *mut to a &mut since we never use the pointer directly in the success case
-    //  and the reference isn't passed to the failure case.
-    if let Some(to_ref) = unsafe { &mut *raw }.downcast_mut() {
-      // SAFETY:
-      // The pointer originates from a `Box` with the same dynamic type,
-      //  since we only changed the pointer metadata.
-      Ok(unsafe { Box::from_raw(to_ref) })
-    } else {
-      // SAFETY:
-      // We reconstruct the previously destructed `Box`.
-      Err(unsafe { Self::from_raw(raw) })
-    }
-  }
-  #[cfg(feature = "downcast_unchecked")]
-  default unsafe fn downcast_unchecked(self) -> Self::Output {
-    // SAFETY: The caller must ensure that the cast is valid.
-    unsafe { <Self as TraitcastableAnyInfraExt<Target>>::downcast(self).unwrap_unchecked() }
-  }
-}
+  /// The same kind of pointer pointing to `U`. Something like `Box<U>`.
+  type Rebind<U: ?Sized + 'static>;
 
-#[cfg(feature = "alloc")]
-impl<Src: TraitcastableAnyInfra<Target>, Target: Sized + 'static> TraitcastableAnyInfraExt<Target>
-  for Box<Src>
-{
-  fn downcast(self) -> Result<Self::Output, Self> {
-    #[cfg(feature = "downcast_unchecked")]
-    if TraitcastableAnyInfra::<Target>::is(self.as_ref()) {
-      // SAFETY:
-      // We checked for dynamic type equality `is` in the previous if.
-      unsafe { Ok(<Box<dyn Any>>::downcast_unchecked(self)) }
-    } else {
-      Err(self)
-    }
-    #[cfg(not(feature = "downcast_unchecked"))]
-    if TraitcastableAnyInfra::<Target>::is(self.as_ref()) {
-      Ok(<Box<dyn Any>>::downcast::<Target>(self).unwrap())
-    } else {
-      Err(self)
-    }
-  }
+  /// Consumes the pointer and returns the wrapped raw pointer without dropping the pointee.
+  fn into_raw(this: Self) -> *mut Self::Pointee;
 
-  #[cfg(feature = "downcast_unchecked")]
-  unsafe fn downcast_unchecked(self) -> Self::Output {
-    // SAFETY: The caller must ensure that the cast is valid.
-    unsafe { <Box<dyn Any>>::downcast_unchecked::<Target>(self) }
-  }
+  /// Reconstructs the pointer from a raw pointer.
+  ///
+  /// # Safety
+  /// See the safety section of the trait.
+  unsafe fn from_raw(raw: *mut Self::Pointee) -> Self;
 }
 
-#[cfg(feature = "alloc")]
-impl<Src: TraitcastableAnyInfra<Target> + ?Sized, Target: ?Sized + 'static>
-  TraitcastableAnyInfraExt<Target> for Rc<Src>
+impl<P: TraitcastablePointer, Target: ?Sized + 'static> TraitcastableAnyInfraExt<Target> for P
+where
+  P::Pointee: TraitcastableAnyInfra<Target>,
+  P::Rebind<Target>: TraitcastablePointer<Pointee = Target>,
 {
-  type Output = Rc<Target>;
+  type Output = P::Rebind<Target>;
 
-  default fn downcast(self) -> Result<Self::Output, Self> {
-    let raw = Self::into_raw(self);
+  fn downcast(self) -> Result<Self::Output, Self> {
+    let raw = P::into_raw(self);
     // SAFETY:
-    // We can cast the *mut to a &mut since we never use the pointer directly in the success case
+    // The pointer originates from a live pointer, we only use the reference to read the metadata
     //  and the reference isn't passed to the failure case.
-    if let Some(to_ref) = unsafe { &*raw }.downcast_ref() {
+    if let Some(to_ref) = unsafe { &*raw }.downcast_ref_in_place() {
+      let to_ptr: *mut Target = ptr::from_raw_parts_mut(raw.cast::<()>(), ptr::metadata(to_ref));
       // SAFETY:
-      // The pointer originates from a `Rc` with the same dynamic type,
+      // The pointer originates from a `P` with the same dynamic type,
       //  since we only changed the pointer metadata.
-      Ok(unsafe { Rc::from_raw(to_ref) })
+      Ok(unsafe { <P::Rebind<Target>>::from_raw(to_ptr) })
     } else {
       // SAFETY:
-      // We reconstruct the previously destructed `Rc`.
-      Err(unsafe { Self::from_raw(raw) })
+      // We reconstruct the previously destructed `P`.
+      Err(unsafe { P::from_raw(raw) })
     }
   }
   #[cfg(feature = "downcast_unchecked")]
-  default unsafe fn downcast_unchecked(self) -> Self::Output {
+  unsafe fn downcast_unchecked(self) -> Self::Output {
     // SAFETY: The caller must ensure that the cast is valid.
     unsafe { <Self as TraitcastableAnyInfraExt<Target>>::downcast(self).unwrap_unchecked() }
   }
 }
 
 #[cfg(feature = "alloc")]
-impl<Src: TraitcastableAnyInfra<Target>, Target: Sized + 'static> TraitcastableAnyInfraExt<Target>
-  for Rc<Src>
-{
-  fn downcast(self) -> Result<Self::Output, Self> {
-    #[cfg(feature = "downcast_unchecked")]
-    if TraitcastableAnyInfra::<Target>::is(self.as_ref()) {
-      // SAFETY:
-      // We checked for dynamic type equality `is` in the previous if.
-      unsafe { Ok(<Rc<dyn Any>>::downcast_unchecked(self)) }
-    } else {
-      Err(self)
-    }
-    #[cfg(not(feature = "downcast_unchecked"))]
-    if TraitcastableAnyInfra::<Target>::is(self.as_ref()) {
-      Ok(<Rc<dyn Any>>::downcast(self).unwrap())
-    } else {
-      Err(self)
-    }
+// SAFETY:
+// `Box::from_raw` accepts pointers from `Box::into_raw` of the same allocation with any valid metadata.
+unsafe impl<T: ?Sized> TraitcastablePointer for Box<T> {
+  type Pointee = T;
+  type Rebind<U: ?Sized + 'static> = Box<U>;
+
+  fn into_raw(this: Self) -> *mut T {
+    Self::into_raw(this)
   }
-
-  #[cfg(feature = "downcast_unchecked")]
-  unsafe fn downcast_unchecked(self) -> Self::Output {
-    // SAFETY: The caller must ensure that the cast is valid.
-    unsafe { <Rc<dyn Any>>::downcast_unchecked::<Target>(self) }
+  unsafe fn from_raw(raw: *mut T) -> Self {
+    // SAFETY: Guaranteed by the caller.
+    unsafe { Self::from_raw(raw) }
   }
 }
 
 #[cfg(feature = "alloc")]
-impl<
-  Src: TraitcastableAnyInfra<Target> + ?Sized + Send + Sync,
-  Target: ?Sized + 'static + Send + Sync,
-> TraitcastableAnyInfraExt<Target> for Arc<Src>
-{
-  type Output = Arc<Target>;
-
-  default fn downcast(self) -> Result<Self::Output, Self> {
-    let raw = Self::into_raw(self);
-    // SAFETY:
-    // We can cast the *mut to a &mut since we never use the pointer directly in the success case
-    //  and the reference isn't passed to the failure case.
-    if let Some(to_ref) = unsafe { &*raw }.downcast_ref() {
-      // SAFETY:
-      // The pointer originates from a `Arc` with the same dynamic type,
-      //  since we only changed the pointer metadata.
-      Ok(unsafe { Arc::from_raw(to_ref) })
-    } else {
-      // SAFETY:
-      // We reconstruct the previously destructed `Arc`.
-      Err(unsafe { Self::from_raw(raw) })
-    }
+// SAFETY:
+// `Rc::from_raw` accepts pointers from `Rc::into_raw` of the same allocation with any valid metadata.
+unsafe impl<T: ?Sized> TraitcastablePointer for Rc<T> {
+  type Pointee = T;
+  type Rebind<U: ?Sized + 'static> = Rc<U>;
+
+  fn into_raw(this: Self) -> *mut T {
+    Self::into_raw(this).cast_mut()
   }
-  #[cfg(feature = "downcast_unchecked")]
-  default unsafe fn downcast_unchecked(self) -> Self::Output {
-    // SAFETY: The caller must ensure that the cast is valid.
-    unsafe { <Self as TraitcastableAnyInfraExt<Target>>::downcast(self).unwrap_unchecked() }
+  unsafe fn from_raw(raw: *mut T) -> Self {
+    // SAFETY: Guaranteed by the caller.
+    unsafe { Self::from_raw(raw.cast_const()) }
   }
 }
 
 #[cfg(feature = "alloc")]
-impl<Src: TraitcastableAnyInfra<Target> + Send + Sync, Target: Sized + 'static + Send + Sync>
-  TraitcastableAnyInfraExt<Target> for Arc<Src>
-{
-  fn downcast(self) -> Result<Self::Output, Self> {
-    #[cfg(feature = "downcast_unchecked")]
-    if TraitcastableAnyInfra::<Target>::is(self.as_ref()) {
-      // SAFETY:
-      // We checked for dynamic type equality `is` in the previous if.
-      unsafe { Ok(<Arc<dyn Any + Send + Sync>>::downcast_unchecked(self)) }
-    } else {
-      Err(self)
-    }
-    #[cfg(not(feature = "downcast_unchecked"))]
-    if TraitcastableAnyInfra::<Target>::is(self.as_ref()) {
-      Ok(<Arc<dyn Any + Send + Sync>>::downcast(self).unwrap())
-    } else {
-      Err(self)
-    }
+// SAFETY:
+// `Arc::from_raw` accepts pointers from `Arc::into_raw` of the same allocation with any valid metadata.
+unsafe impl<T: ?Sized> TraitcastablePointer for Arc<T> {
+  type Pointee = T;
+  type Rebind<U: ?Sized + 'static> = Arc<U>;
+
+  fn into_raw(this: Self) -> *mut T {
+    Self::into_raw(this).cast_mut()
   }
-
-  #[cfg(feature = "downcast_unchecked")]
-  unsafe fn downcast_unchecked(self) -> Self::Output {
-    // SAFETY: The caller must ensure that the cast is valid.
-    unsafe { <Arc<dyn Any + Send + Sync>>::downcast_unchecked::<Target>(self) }
+  unsafe fn from_raw(raw: *mut T) -> Self {
+    // SAFETY: Guaranteed by the caller.
+    unsafe { Self::from_raw(raw.cast_const()) }
   }
 }
 
@@ -0,0 +1,298 @@
+use core::{
+  any::{Any, TypeId},
+  iter,
+};
+
+use alloc::{
+  boxed::Box,
+  collections::{BTreeMap, BTreeSet},
+  vec::Vec,
+};
+
+use crate::{TraitcastTarget, TraitcastableAny, TraitcastableAnyInfra};
+
+/// A stable handle to an object in a `TraitMap`.
+///
+/// Stays valid until the object is removed. Handles of removed objects are never reused.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct TraitMapKey {
+  index: usize,
+  generation: u64,
+}
+
+#[derive(Debug)]
+struct Slot {
+  generation: u64,
+  object: Option<Box<dyn TraitcastableAny>>,
+  /// The `TypeId`s under which the object is indexed.
+  type_ids: Vec<TypeId>,
+}
+
+/// A heterogeneous container of trait castable objects, which is indexed by their cast targets.
+///
+/// The `TypeId`s of the concrete type and of the `traitcast_targets` of every object are recorded when it is inserted.
+/// So iterating over the objects castable to a trait or of a concrete type only visits the matching objects.
+///
+/// Targets reached through a delegate or provided values are not indexed, since they can't be enumerated.
+/// Guarded targets are indexed regardless of their guard, which is checked whenever an object is looked up.
+///
+/// The targets of an object must not change while it is in the map, or it is not found as its new targets.
+/// Call `reindex` after changing the targets of an object through `object_mut` or `iter_mut`, for example
+///  after adding a component to a `Composite`.
+#[derive(Debug, Default)]
+pub struct TraitMap {
+  slots: Vec<Slot>,
+  free: Vec<usize>,
+  /// The indices of the slots of the objects castable to the type with the `TypeId`.
+  indices: BTreeMap<TypeId, BTreeSet<usize>>,
+  len: usize,
+}
+
+/// Returns the `TypeId`s under which the object is indexed.
+fn indexed_type_ids(object: &dyn TraitcastableAny) -> impl Iterator<Item = TypeId> + '_ {
+  let any: &dyn Any = object;
+  iter::once(any.type_id()).chain(
+    object
+      .traitcast_targets()
+      .iter()
+      .map(TraitcastTarget::target_type_id),
+  )
+}
+
+/// Removes the slot with the index from the indices of the `TypeId`s.
+fn unindex(indices: &mut BTreeMap<TypeId, BTreeSet<usize>>, index: usize, type_ids: &[TypeId]) {
+  for type_id in type_ids {
+    if let Some(slots) = indices.get_mut(type_id) {
+      slots.remove(&index);
+      if slots.is_empty() {
+        indices.remove(type_id);
+      }
+    }
+  }
+}
+
+impl TraitMap {
+  /// Creates an empty map.
+  #[must_use]
+  pub const fn new() -> Self {
+    Self {
+      slots: Vec::new(),
+      free: Vec::new(),
+      indices: BTreeMap::new(),
+      len: 0,
+    }
+  }
+
+  /// Returns the number of objects in the map.
+  #[must_use]
+  pub const fn len(&self) -> usize {
+    self.len
+  }
+
+  /// Returns true if the map contains no objects.
+  #[must_use]
+  pub const fn is_empty(&self) -> bool {
+    self.len == 0
+  }
+
+  /// Inserts the object and returns its handle.
+  pub fn insert(&mut self, object: Box<dyn TraitcastableAny>) -> TraitMapKey {
+    let index = self.free.pop().unwrap_or_else(|| {
+      self.slots.push(Slot {
+        generation: 0,
+        object: None,
+        type_ids: Vec::new(),
+      });
+      self.slots.len() - 1
+    });
+    let type_ids: Vec<TypeId> = indexed_type_ids(object.as_ref()).collect();
+    for &type_id in &type_ids {
+      self.indices.entry(type_id).or_default().insert(index);
+    }
+    let slot = &mut self.slots[index];
+    slot.object = Some(object);
+    slot.type_ids = type_ids;
+    self.len += 1;
+    TraitMapKey {
+      index,
+      generation: slot.generation,
+    }
+  }
+
+  /// Removes the object with the handle and returns it.
+  ///
+  /// Returns `None` if the object was already removed.
+  pub fn remove(&mut self, key: TraitMapKey) -> Option<Box<dyn TraitcastableAny>> {
+    let slot = self
+      .slots
+      .get_mut(key.index)
+      .filter(|slot| slot.generation == key.generation)?;
+    let object = slot.object.take()?;
+    slot.generation += 1;
+    unindex(&mut self.indices, key.index, &slot.type_ids);
+    slot.type_ids.clear();
+    self.free.push(key.index);
+    self.len -= 1;
+    Some(object)
+  }
+
+  /// Indexes the object with the handle under its current targets.
+  ///
+  /// Has to be called after the targets of the object were changed through `object_mut` or `iter_mut`.
+  /// Returns false if the object was already removed.
+  pub fn reindex(&mut self, key: TraitMapKey) -> bool {
+    let Some(slot) = self
+      .slots
+      .get_mut(key.index)
+      .filter(|slot| slot.generation == key.generation)
+    else {
+      return false;
+    };
+    let Some(object) = slot.object.as_deref() else {
+      return false;
+    };
+    unindex(&mut self.indices, key.index, &slot.type_ids);
+    slot.type_ids.clear();
+    slot.type_ids.extend(indexed_type_ids(object));
+    for &type_id in &slot.type_ids {
+      self.indices.entry(type_id).or_default().insert(key.index);
+    }
+    true
+  }
+
+  /// Returns true if the map contains the object with the handle.
+  #[must_use]
+  pub fn contains_key(&self, key: TraitMapKey) -> bool {
+    self.object(key).is_some()
+  }
+
+  /// Returns the object with the handle.
+  #[must_use]
+  pub fn object(&self, key: TraitMapKey) -> Option<&dyn TraitcastableAny> {
+    let slot = self
+      .slots
+      .get(key.index)
+      .filter(|slot| slot.generation == key.generation)?;
+    slot.object.as_deref()
+  }
+
+  /// Returns the object with the handle mutably.
+  #[must_use]
+  pub fn object_mut(&mut self, key: TraitMapKey) -> Option<&mut dyn TraitcastableAny> {
+    let slot = self
+      .slots
+      .get_mut(key.index)
+      .filter(|slot| slot.generation == key.generation)?;
+    slot.object.as_deref_mut()
+  }
+
+  /// Returns the object with the handle as `Target`.
+  ///
+  /// Returns `None` if the object was removed or can't be cast to `Target`.
+  #[must_use]
+  pub fn cast<Target: ?Sized + 'static>(&self, key: TraitMapKey) -> Option<&Target> {
+    self.object(key)?.downcast_ref()
+  }
+
+  /// Returns the object with the handle mutably as `Target`.
+  ///
+  /// Returns `None` if the object was removed or can't be cast to `Target`.
+  #[must_use]
+  pub fn cast_mut<Target: ?Sized + 'static>(&mut self, key: TraitMapKey) -> Option<&mut Target> {
+    self.object_mut(key)?.downcast_mut()
+  }
+
+  /// Returns the first object of the concrete type `T`.
+  #[must_use]
+  pub fn get<T: TraitcastableAny>(&self) -> Option<&T> {
+    self.iter::<T>().next().map(|(_, object)| object)
+  }
+
+  /// Returns the first object of the concrete type `T` mutably.
+  #[must_use]
+  pub fn get_mut<T: TraitcastableAny>(&mut self) -> Option<&mut T> {
+    self.iter_mut::<T>().next().map(|(_, object)| object)
+  }
+
+  /// Returns the number of objects castable to `Target`.
+  #[must_use]
+  pub fn count<Target: ?Sized + 'static>(&self) -> usize {
+    self.iter::<Target>().count()
+  }
+
+  fn index_of<Target: ?Sized + 'static>(&self) -> Option<&BTreeSet<usize>> {
+    self.indices.get(&TypeId::of::<Target>())
+  }
+
+  fn key_at(&self, index: usize) -> Option<(TraitMapKey, &dyn TraitcastableAny)> {
+    let slot = &self.slots[index];
+    let key = TraitMapKey {
+      index,
+      generation: slot.generation,
+    };
+    Some((key, slot.object.as_deref()?))
+  }
+
+  /// Iterates over the objects castable to `Target`, which is a trait object or a concrete type.
+  ///
+  /// Only visits the matching objects, in the order of their slots.
+  pub fn iter<Target: ?Sized + 'static>(&self) -> impl Iterator<Item = (TraitMapKey, &Target)> {
+    self
+      .index_of::<Target>()
+      .into_iter()
+      .flatten()
+      .filter_map(|&index| {
+        let (key, object) = self.key_at(index)?;
+        Some((key, object.downcast_ref()?))
+      })
+  }
+
+  /// Mutable variant of `iter`.
+  pub fn iter_mut<Target: ?Sized + 'static>(
+    &mut self,
+  ) -> impl Iterator<Item = (TraitMapKey, &mut Target)> {
+    let mut slots = self.slots.iter_mut().enumerate();
+    let mut next_index = 0;
+    self
+      .indices
+      .get(&TypeId::of::<Target>())
+      .into_iter()
+      .flatten()
+      .filter_map(move |&index| {
+        // The indices are sorted, so skipping to the next match keeps this `O(matches)`.
+        let (index, slot) = slots.nth(index - next_index)?;
+        next_index = index + 1;
+        let key = TraitMapKey {
+          index,
+          generation: slot.generation,
+        };
+        Some((key, slot.object.as_deref_mut()?.downcast_mut()?))
+      })
+  }
+
+  /// Iterates over the objects castable to both `A` and `B`.
+  ///
+  /// Only visits the objects castable to the rarer of the two.
+  pub fn iter_both<A: ?Sized + 'static, B: ?Sized + 'static>(
+    &self,
+  ) -> impl Iterator<Item = (TraitMapKey, &A, &B)> {
+    let (a, b) = (self.index_of::<A>(), self.index_of::<B>());
+    let (smaller, larger) = match (a, b) {
+      (Some(a), Some(b)) if b.len() < a.len() => (Some(b), Some(a)),
+      _ => (a, b),
+    };
+    smaller
+      .into_iter()
+      .flatten()
+      .filter(move |index| larger.is_some_and(|larger| larger.contains(index)))
+      .filter_map(|&index| {
+        let (key, object) = self.key_at(index)?;
+        Some((key, object.downcast_ref()?, object.downcast_ref()?))
+      })
+  }
+
+  /// Iterates over all objects, in the order of their slots.
+  pub fn objects(&self) -> impl Iterator<Item = (TraitMapKey, &dyn TraitcastableAny)> {
+    (0..self.slots.len()).filter_map(|index| self.key_at(index))
+  }
+}
@@ -0,0 +1,96 @@
+use core::{
+  alloc::Layout,
+  mem::ManuallyDrop,
+  ptr::{self, Pointee},
+};
+
+use alloc::{
+  alloc::{alloc, handle_alloc_error},
+  boxed::Box,
+  rc::Rc,
+  sync::Arc,
+};
+
+use crate::TraitcastableAnyInfra;
+
+/// Extension Trait to implement over shared Smart Pointer Types (`Rc`, `Arc`).
+///
+/// Allows casting out of a shared pointer once it is no longer shared.
+pub trait TraitcastableUniqueExt<Target: ?Sized + 'static>: Sized {
+  /// Moves the value out of a uniquely owned pointer into a `Box<Target>`.
+  ///
+  /// The pointer is uniquely owned if there are no other strong and no weak references to it.
+  ///
+  /// # Errors
+  /// In case the pointer is shared or a cast is impossible the original input is returned as the error type.
+  fn try_downcast_unique(self) -> Result<Box<Target>, Self>;
+
+  /// Same as `Rc::get_mut` and `Arc::get_mut`, except that it additionally downcasts to `Target`.
+  ///
+  /// Returns `None` if the pointer is shared or a cast is impossible.
+  fn downcast_get_mut(&mut self) -> Option<&mut Target>;
+}
+
+/// Moves the value behind `value` into a new `Box`.
+///
+/// # Safety
+/// `value` must point to a valid `T`, which must not be used or dropped afterwards.
+unsafe fn move_into_box<T: ?Sized>(value: *const T) -> Box<T> {
+  // SAFETY: The caller guarantees that `value` points to a valid `T`.
+  let layout = Layout::for_value(unsafe { &*value });
+  let (data, metadata) = value.to_raw_parts();
+  let dst = if layout.size() == 0 {
+    ptr::without_provenance_mut::<u8>(layout.align())
+  } else {
+    // SAFETY: The layout has a non zero size.
+    let dst = unsafe { alloc(layout) };
+    if dst.is_null() {
+      handle_alloc_error(layout);
+    }
+    dst
+  };
+  // SAFETY:
+  // `dst` was just allocated with the layout of the value and can't overlap with it.
+  unsafe { ptr::copy_nonoverlapping(data.cast::<u8>(), dst, layout.size()) };
+  // SAFETY:
+  // `dst` was allocated by the global allocator with `Layout::for_value` (or is dangling for zero sized values),
+  //  and now contains a bitwise copy of the value.
+  unsafe { Box::from_raw(ptr::from_raw_parts_mut::<T>(dst.cast::<()>(), metadata)) }
+}
+
+macro_rules! implement_unique {
+  ($ptr:ident) => {
+    impl<Src: TraitcastableAnyInfra<Target> + ?Sized, Target: ?Sized + 'static>
+      TraitcastableUniqueExt<Target> for $ptr<Src>
+    {
+      fn try_downcast_unique(mut self) -> Result<Box<Target>, Self> {
+        let Some(target) = $ptr::get_mut(&mut self).and_then(|src| src.downcast_mut_in_place())
+        else {
+          return Err(self);
+        };
+        let metadata: <Target as Pointee>::Metadata = ptr::metadata(target);
+
+        let raw = $ptr::into_raw(self);
+        let value: *const Target = ptr::from_raw_parts(raw.cast::<()>(), metadata);
+        // SAFETY:
+        // We checked that we own the only reference, so the value is moved out exactly once.
+        let boxed = unsafe { move_into_box(value) };
+        // `ManuallyDrop` is `repr(transparent)`, so it has the same layout as `Src`,
+        //  which lets `from_raw` free the allocation without dropping the moved out value again.
+        let moved_out: *const ManuallyDrop<Src> =
+          ptr::from_raw_parts(raw.cast::<()>(), ptr::metadata(raw));
+        // SAFETY:
+        // The pointer originates from `into_raw` of the only reference and `ManuallyDrop<Src>` has the layout of `Src`.
+        drop(unsafe { $ptr::from_raw(moved_out) });
+        Ok(boxed)
+      }
+
+      fn downcast_get_mut(&mut self) -> Option<&mut Target> {
+        $ptr::get_mut(self).and_then(|src| src.downcast_mut())
+      }
+    }
+  };
+}
+
+implement_unique!(Rc);
+implement_unique!(Arc);
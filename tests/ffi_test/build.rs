@@ -0,0 +1,28 @@
+//! Generates the C header of the `ffi` module and compiles the C driver against it.
+use std::{env, path::PathBuf};
+
+fn main() {
+  let out_dir = PathBuf::from(env::var_os("OUT_DIR").unwrap());
+  let ffi = PathBuf::from(env::var_os("CARGO_MANIFEST_DIR").unwrap()).join("../../src/ffi.rs");
+  println!("cargo::rerun-if-changed={}", ffi.display());
+  println!("cargo::rerun-if-changed=c/driver.c");
+
+  cbindgen::Builder::new()
+    .with_src(ffi)
+    .with_language(cbindgen::Language::C)
+    .with_include_guard("TRAIT_CAST_H")
+    .with_header("/* Generated by cbindgen from `src/ffi.rs`. Do not edit. */")
+    .with_cpp_compat(true)
+    .with_sys_include("stdbool.h")
+    .with_sys_include("stdint.h")
+    .with_no_includes()
+    .generate()
+    .unwrap()
+    .write_to_file(out_dir.join("trait_cast.h"));
+
+  cc::Build::new()
+    .file("c/driver.c")
+    .include(&out_dir)
+    .warnings_into_errors(true)
+    .compile("driver");
+}
@@ -0,0 +1,53 @@
+/* Drives the `ffi` module of trait-cast like a C host application would. */
+#include <stddef.h>
+
+#include "trait_cast.h"
+
+/* Implemented in Rust by `src/main.rs`. */
+int32_t ffi_test_greeter_greeting(const TraitcastHandle *greeter);
+uint32_t ffi_test_counter_increment(const TraitcastHandle *counter);
+
+#define CHECK(condition) \
+  if (!(condition)) { \
+    return __LINE__; \
+  }
+
+/* Returns zero on success or the line of the failed check. Takes ownership of `object`. */
+int ffi_test_drive(const TraitcastHandle *object, uint64_t greeter_id, uint64_t counter_id,
+                   uint64_t unsupported_id) {
+  CHECK(trait_cast_handle_interface_id(object) == 0);
+  CHECK(trait_cast_handle_supports(object, greeter_id));
+  CHECK(trait_cast_handle_supports(object, counter_id));
+  CHECK(!trait_cast_handle_supports(object, unsupported_id));
+  CHECK(trait_cast_handle_query_interface(object, unsupported_id) == NULL);
+
+  const TraitcastHandle *greeter = trait_cast_handle_query_interface(object, greeter_id);
+  CHECK(greeter != NULL);
+  CHECK(trait_cast_handle_interface_id(greeter) == greeter_id);
+  CHECK(trait_cast_handle_same_object(object, greeter));
+  CHECK(ffi_test_greeter_greeting(greeter) == 42);
+
+  /* Interfaces can be queried from other interfaces. */
+  const TraitcastHandle *counter = trait_cast_handle_query_interface(greeter, counter_id);
+  CHECK(counter != NULL);
+  CHECK(ffi_test_counter_increment(counter) == 1);
+  /* Rust functions reject handles for other interfaces. */
+  CHECK(ffi_test_greeter_greeting(counter) == -1);
+
+  /* Handles stay valid as long as they are retained. */
+  CHECK(trait_cast_handle_retain(counter) == counter);
+  trait_cast_handle_release(counter);
+  CHECK(ffi_test_counter_increment(counter) == 2);
+
+  trait_cast_handle_release(counter);
+  trait_cast_handle_release(greeter);
+  trait_cast_handle_release(object);
+
+  /* Null handles are accepted everywhere. */
+  CHECK(trait_cast_handle_retain(NULL) == NULL);
+  trait_cast_handle_release(NULL);
+  CHECK(!trait_cast_handle_supports(NULL, greeter_id));
+  CHECK(trait_cast_handle_query_interface(NULL, greeter_id) == NULL);
+  CHECK(!trait_cast_handle_same_object(NULL, NULL));
+  return 0;
+}
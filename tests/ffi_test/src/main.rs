@@ -0,0 +1,100 @@
+#![cfg_attr(feature = "min_specialization", feature(min_specialization))]
+#![feature(ptr_metadata)]
+#![allow(
+  unsafe_code,
+  reason = "The test exports functions to and calls functions of the C driver."
+)]
+use std::sync::{
+  Arc,
+  atomic::{AtomicU32, Ordering},
+};
+
+use trait_cast::{StableTypeId, ffi::TraitcastHandle, make_trait_castable, traitcast};
+
+#[traitcast(id = "trait_cast.ffi_test.Greeter.v1")]
+trait Greeter {
+  fn greeting(&self) -> i32;
+}
+
+#[traitcast(id = "trait_cast.ffi_test.Counter.v1")]
+trait Counter {
+  fn increment(&self) -> u32;
+}
+
+#[traitcast(id = "trait_cast.ffi_test.Unsupported.v1")]
+trait Unsupported {}
+
+#[make_trait_castable(#[stable] Greeter, #[stable] Counter)]
+struct Widget {
+  count: AtomicU32,
+}
+
+impl Greeter for Widget {
+  fn greeting(&self) -> i32 {
+    42
+  }
+}
+impl Counter for Widget {
+  fn increment(&self) -> u32 {
+    self.count.fetch_add(1, Ordering::Relaxed) + 1
+  }
+}
+
+/// Returns the greeting of the greeter or -1 if the handle is not for the `Greeter` interface.
+#[unsafe(no_mangle)]
+extern "C" fn ffi_test_greeter_greeting(greeter: *const TraitcastHandle) -> i32 {
+  // SAFETY: The C driver only passes handles which are still alive.
+  let greeter = unsafe { TraitcastHandle::from_raw(greeter) }.unwrap();
+  // SAFETY: `dyn Greeter` is the only type with its `StableId`.
+  unsafe { greeter.downcast_interface::<dyn Greeter>() }.map_or(-1, Greeter::greeting)
+}
+
+/// Increments the counter and returns the new count or 0 if the handle is not for the `Counter` interface.
+#[unsafe(no_mangle)]
+extern "C" fn ffi_test_counter_increment(counter: *const TraitcastHandle) -> u32 {
+  // SAFETY: The C driver only passes handles which are still alive.
+  let counter = unsafe { TraitcastHandle::from_raw(counter) }.unwrap();
+  // SAFETY: `dyn Counter` is the only type with its `StableId`.
+  unsafe { counter.downcast_interface::<dyn Counter>() }.map_or(0, Counter::increment)
+}
+
+#[expect(improper_ctypes, reason = "The handle is opaque to C.")]
+unsafe extern "C" {
+  fn ffi_test_drive(
+    object: *const TraitcastHandle,
+    greeter_id: u64,
+    counter_id: u64,
+    unsupported_id: u64,
+  ) -> i32;
+}
+
+#[cfg_attr(test, test)]
+fn main() {
+  let widget = Arc::new(Widget {
+    count: AtomicU32::new(0),
+  });
+  let handle = TraitcastHandle::into_raw(widget.clone());
+  // SAFETY: The driver takes ownership of the handle.
+  let failed_line = unsafe {
+    ffi_test_drive(
+      handle,
+      <dyn Greeter as StableTypeId>::STABLE_ID.to_raw(),
+      <dyn Counter as StableTypeId>::STABLE_ID.to_raw(),
+      <dyn Unsupported as StableTypeId>::STABLE_ID.to_raw(),
+    )
+  };
+  assert_eq!(
+    failed_line, 0,
+    "check in line {failed_line} of c/driver.c failed"
+  );
+  assert_eq!(widget.count.load(Ordering::Relaxed), 2);
+  // All handles were released.
+  assert_eq!(Arc::strong_count(&widget), 1);
+
+  let generated_header = include_str!(concat!(env!("OUT_DIR"), "/trait_cast.h"));
+  let header = include_str!("../../../include/trait_cast.h");
+  assert_eq!(
+    generated_header, header,
+    "include/trait_cast.h is outdated, replace it with the generated header"
+  );
+}